@@ -1,7 +1,21 @@
+mod budget;
+pub mod chat;
+mod confidence;
+pub mod few_shot;
+mod metrics;
 mod ollama;
+mod openai;
+mod parsing;
+mod repair;
+mod retry;
+pub mod similarity;
+
+pub use metrics::InferenceStats;
+pub use repair::RepairCounter;
 use thiserror::Error as ThisError;
 use crate::configuration::settings;
 use crate::models;
+use parsing::ResponseParser;
 
 /// An abstraction of a Large Language Model client (e.g., Ollama), to provide 
 /// an interface to requests arguments summarization and relations prediction.
@@ -13,8 +27,12 @@ use crate::models;
 /// 
 /// let summarized_argument = client.summarize("An example argument").unwrap();
 /// ```
+// Clients live for the whole subcommand and are never moved around, so the
+// Ollama variant is kept unboxed.
+#[allow(clippy::large_enum_variant)]
 pub enum Client {
     Ollama(ollama::Client),
+    OpenAICompatible(openai::Client),
 }
 
 /// The different kinds of error expected from a client.
@@ -22,12 +40,22 @@ pub enum Client {
 pub enum Error {    
     #[error("ollama client error: {0}")]
     Ollama(#[from] ollama::Error),
+
+    #[error("openai-compatible client error: {0}")]
+    OpenAICompatible(#[from] openai::Error),
+
+    #[error("context window error: {0}")]
+    Budget(#[from] budget::Error),
 }
 
 impl Client {
     /// Create a new Large Language Model client from a YAML configuration file.
-    /// For now the only accepted configuration is a configuration for an Ollama
-    /// client.
+    /// The accepted configurations are a configuration for an Ollama client or
+    /// for an OpenAI-compatible chat completions client. For an ensemble
+    /// configuration the client of its first member is created, which is the
+    /// one summarizing arguments and computing the embeddings of the
+    /// pre-filter and of the few-shot example selection, only the relation
+    /// predictions being made by every member.
     /// 
     /// # Arguments
     /// 
@@ -47,36 +75,283 @@ impl Client {
     /// ```rs
     /// let client = Client::new("/path/to/client/config.yml");
     /// 
-    /// let summarized_argument = client.summarize("An example argument").unwrap();
+    /// let summarized_arguments = client.summarize("An example argument").unwrap();
     /// ```
     pub fn new(cfg: &settings::LLMCfg) -> Self {
         match cfg {
             settings::LLMCfg::Ollama(ollama_cfg) =>
                 Client::Ollama(ollama::Client::new(ollama_cfg)),
+            settings::LLMCfg::OpenAICompatible(openai_cfg) =>
+                Client::OpenAICompatible(openai::Client::new(openai_cfg)),
+            settings::LLMCfg::Ensemble(ensemble_cfg) => {
+                let member = &ensemble_cfg.members[0].llm;
+
+                log::info!("using the first ensemble member ({}) outside of relation prediction", member.model_name());
+
+                Client::new(member)
+            },
+        }
+    }
+
+    fn summarize_budget(&self) -> &budget::Budget {
+        match self {
+            Client::Ollama(client) => client.summarize_budget(),
+            Client::OpenAICompatible(client) => client.summarize_budget(),
+        }
+    }
+
+    fn predict_budget(&self) -> &budget::Budget {
+        match self {
+            Client::Ollama(client) => client.predict_budget(),
+            Client::OpenAICompatible(client) => client.predict_budget(),
+        }
+    }
+
+    /// Whether the answers of the few-shot demonstrations are sent as
+    /// structured outputs.
+    fn structured_outputs(&self) -> bool {
+        match self {
+            Client::Ollama(client) => client.structured_outputs(),
+            Client::OpenAICompatible(_) => false,
         }
     }
 }
 
+/// Estimate the number of tokens of a prompt along with its system prompt.
+fn prompt_tokens(budget: &budget::Budget, prompt: &settings::Prompt, rendered: &str) -> usize {
+    budget.estimate(rendered) + prompt.system.as_deref().map_or(0, |system| budget.estimate(system))
+}
+
 /// Trait defining all the Large Language Model client expected features for
 /// this program.
 pub trait ClientTrait {
-    /// Summarize the underlying argument of a user generated web-content
-    /// (e.g., Twitter Post)
-    async fn summarize(&self, prompt: &settings::Prompt, content: String) -> Result<models::SummarizedInfo, Error>;
+    /// Summarize the underlying arguments of a user generated web-content
+    /// (e.g., Twitter Post), in order of appearance, with the given few-shot
+    /// demonstrations. The summaries of the chunks of a post split with the
+    /// `MapReduce` overflow handling are merged with the `reduce` prompt.
+    async fn summarize(&self, prompt: &settings::Prompt, reduce: Option<&settings::Prompt>, post: &models::Post, examples: &[models::SummarizeExample]) -> Result<Vec<models::SummarizedInfo>, Error>;
+
+    /// Merge the partial summaries of the chunks of a post into one
+    /// summarized argument.
+    async fn reduce(&self, prompt: &settings::Prompt, post: &models::Post, summaries: &[models::SummarizedInfo]) -> Result<models::SummarizedInfo, Error>;
 
-    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument) -> Result<models::Relation, Error>;
+    /// Predict the relation of `arg_a` toward `arg_b` with the given few-shot
+    /// demonstrations, among the relations of the configured taxonomy and
+    /// estimating its confidence with the configured method. The prompt
+    /// continues the conversation of `history`, if any.
+    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], cfg: &settings::PredictionCfg, history: &[chat::Message]) -> Result<chat::Prediction, Error>;
+
+    /// Compute the embedding vector of a text with the configured embedding
+    /// model.
+    async fn embed(&self, content: &str) -> Result<Vec<f32>, Error>;
+
+    /// Return the usage of the inferences of the client so far.
+    fn inference_stats(&self) -> metrics::InferenceStats;
 }
 
 impl ClientTrait for Client {
-    async fn summarize(&self, prompt: &settings::Prompt, content: String) -> Result<models::SummarizedInfo, Error> {
+    /// Summarize the post, or the pieces of the post fitting in the context
+    /// window of the model. With map-reduce summarization, the summaries of
+    /// the pieces are then merged into one.
+    async fn summarize(&self, prompt: &settings::Prompt, reduce: Option<&settings::Prompt>, post: &models::Post, examples: &[models::SummarizeExample]) -> Result<Vec<models::SummarizedInfo>, Error> {
+        let budget = self.summarize_budget();
+        let demonstrations = parsing::render_demonstrations(&parsing::summarize_demonstrations(examples, self.structured_outputs(), &parsing::Parser::new(&prompt.format)));
+
+        let posts = budget.fit_post(post, |post| prompt_tokens(budget, prompt, &prompt.render_summarize(post, &demonstrations)))?;
+        let mut infos = Vec::new();
+
+        for post in posts.iter() {
+            infos.extend(match self {
+                Client::Ollama(client) => client.summarize(prompt, None, post, examples).await?,
+                Client::OpenAICompatible(client) => client.summarize(prompt, None, post, examples).await?,
+            });
+        }
+
+        match reduce {
+            Some(reduce) if posts.len() > 1 && infos.len() > 1 && budget.reduces() =>
+                Ok(vec![self.reduce(reduce, post, &infos).await?]),
+            _ => Ok(infos),
+        }
+    }
+
+    /// Merge the partial summaries in groups fitting in the context window of
+    /// the model, until one summary is left.
+    async fn reduce(&self, prompt: &settings::Prompt, post: &models::Post, summaries: &[models::SummarizedInfo]) -> Result<models::SummarizedInfo, Error> {
+        let budget = self.summarize_budget();
+        let parser = parsing::Parser::new(&prompt.format);
+        let mut summaries = summaries.to_vec();
+
+        loop {
+            let groups = budget.fit_summaries(&summaries, |summaries| {
+                prompt_tokens(budget, prompt, &prompt.render_reduce(post, &parser.format_summarize_response(summaries)))
+            })?;
+
+            log::debug!("merging {} partial summaries of a post in {} groups", summaries.len(), groups.len());
+
+            let mut merged = Vec::with_capacity(groups.len());
+
+            for group in groups.iter() {
+                merged.push(match (self, group.as_slice()) {
+                    (_, [summary]) => summary.clone(),
+                    (Client::Ollama(client), group) => client.reduce(prompt, post, group).await?,
+                    (Client::OpenAICompatible(client), group) => client.reduce(prompt, post, group).await?,
+                });
+            }
+
+            if merged.len() == 1 {
+                return Ok(merged.remove(0))
+            }
+
+            summaries = merged;
+        }
+    }
+
+    /// Predict the relation of the arguments, truncated if they do not fit in
+    /// the context window of the model.
+    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], cfg: &settings::PredictionCfg, history: &[chat::Message]) -> Result<chat::Prediction, Error> {
+        let budget = self.predict_budget();
+        let demonstrations = parsing::render_demonstrations(&parsing::predict_demonstrations(examples, &prompt.arguments, self.structured_outputs(), &parsing::Parser::new(&prompt.format)));
+        let transcript = chat::transcript(history);
+
+        let (arg_a, arg_b) = &budget.fit_arguments(arg_a, arg_b, |arg_a, arg_b| {
+            prompt_tokens(budget, prompt, &(transcript.clone() + &prompt.render_predict(arg_a, arg_b, &demonstrations)))
+        })?;
+
         match self {
-            Client::Ollama(client) => client.summarize(prompt, content).await,
+            Client::Ollama(client) => client.predict(prompt, arg_a, arg_b, examples, cfg, history).await,
+            Client::OpenAICompatible(client) => client.predict(prompt, arg_a, arg_b, examples, cfg, history).await,
         }
     }
 
-    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument) -> Result<models::Relation, Error> {
+    async fn embed(&self, content: &str) -> Result<Vec<f32>, Error> {
         match self {
-            Client::Ollama(client) => client.predict(prompt, arg_a, arg_b).await,
+            Client::Ollama(client) => client.embed(content).await,
+            Client::OpenAICompatible(client) => client.embed(content).await,
+        }
+    }
+
+    fn inference_stats(&self) -> metrics::InferenceStats {
+        match self {
+            Client::Ollama(client) => client.inference_stats(),
+            Client::OpenAICompatible(client) => client.inference_stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(template: &str) -> settings::Prompt {
+        settings::Prompt {
+            system: None,
+            prompt: String::from(template),
+            format: settings::ResponseFormatCfg::KeyValue,
+            arguments: settings::ArgumentFieldsCfg::Raw,
+            endpoint: settings::EndpointCfg::Generate,
+        }
+    }
+
+    fn client(uri: String, context: settings::ContextCfg) -> Client {
+        Client::new(&settings::LLMCfg::Ollama(settings::OllamaCfg {
+            uri,
+            model: String::from("test-model"),
+            embedding_model: None,
+            structured_outputs: false,
+            options: settings::ModelOptions::default(),
+            summarize_options: settings::ModelOptions::default(),
+            predict_options: settings::ModelOptions::default(),
+            retry: settings::RetryCfg::default(),
+            context,
+        }))
+    }
+
+    fn generation(response: &str) -> String {
+        serde_json::json!({
+            "model": "test-model",
+            "response": response,
+            "done": true,
+        }).to_string()
+    }
+
+    mod summarize {
+        use super::*;
+
+        #[tokio::test]
+        async fn merges_chunk_summaries() {
+            let mut server = mockito::Server::new_async().await;
+            let map = server.mock("POST", "/api/generate")
+                .match_body(mockito::Matcher::Regex(String::from("Summarize the argument")))
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Cut taxes."))
+                .expect(2)
+                .create_async()
+                .await;
+            let reduce = server.mock("POST", "/api/generate")
+                .match_body(mockito::Matcher::Regex(String::from("Merge the summaries")))
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Taxes should be cut."))
+                .expect(1)
+                .create_async()
+                .await;
+
+            let client = client(server.url(), settings::ContextCfg {
+                window_tokens: Some(100),
+                response_tokens: 0,
+                chars_per_token: 1.0,
+                overflow: settings::OverflowCfg::MapReduce,
+                chunk_tokens: None,
+                overlap_tokens: 0,
+            });
+
+            let infos = client
+                .summarize(
+                    &prompt("Summarize the argument."),
+                    Some(&prompt("Merge the summaries.")),
+                    &models::Post {
+                        content: String::from("Taxes are too high for most households, who struggle to pay their bills. \
+                            Public spending should be cut instead of raising taxes again."),
+                        author: None,
+                        thread_context: None,
+                    },
+                    &[],
+                )
+                .await
+                .unwrap();
+
+            map.assert_async().await;
+            reduce.assert_async().await;
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].summary, "Taxes should be cut.");
+        }
+
+        #[tokio::test]
+        async fn keeps_long_post_with_default_context() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Cut taxes."))
+                .expect(1)
+                .create_async()
+                .await;
+
+            let infos = client(server.url(), settings::ContextCfg::default())
+                .summarize(
+                    &prompt("Summarize the argument."),
+                    None,
+                    &models::Post {
+                        content: "Taxes are too high for most households. ".repeat(500),
+                        author: None,
+                        thread_context: None,
+                    },
+                    &[],
+                )
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].summary, "Cut taxes.");
         }
     }
 }
@@ -0,0 +1,368 @@
+use thiserror::Error as ThisError;
+
+use crate::configuration::settings::{ContextCfg, OverflowCfg};
+use crate::models;
+
+/// The different kinds of error expected while fitting a prompt in the
+/// context window.
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("prompt of about {0} tokens exceeds the {1} tokens available in the context window")]
+    ContextOverflow(usize, usize),
+}
+
+/// The number of tokens a model accepts in its prompts, estimated from the
+/// length of the prompts.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    /// The number of tokens available for the prompt, if the context window
+    /// is known.
+    available: Option<usize>,
+    chars_per_token: f32,
+    overflow: OverflowCfg,
+    /// The maximum number of tokens of content of a chunk, if capped.
+    chunk_tokens: Option<usize>,
+    overlap_tokens: usize,
+}
+
+impl Budget {
+    /// Create the budget of a model from its context guard configuration,
+    /// the window defaulting to `default_window` if not configured.
+    pub fn new(cfg: &ContextCfg, default_window: Option<usize>) -> Self {
+        Budget {
+            available: cfg.window_tokens
+                .or(default_window)
+                .map(|window| window.saturating_sub(cfg.response_tokens)),
+            chars_per_token: cfg.chars_per_token.max(f32::EPSILON),
+            overflow: cfg.overflow.clone(),
+            chunk_tokens: cfg.chunk_tokens,
+            overlap_tokens: cfg.overlap_tokens,
+        }
+    }
+
+    /// Whether the summaries of the chunks of a post are merged together.
+    pub fn reduces(&self) -> bool {
+        self.overflow == OverflowCfg::MapReduce
+    }
+
+    /// Estimate the number of tokens of a text.
+    pub fn estimate(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.chars_per_token).ceil() as usize
+    }
+
+    /// Return the maximum number of characters of `tokens` tokens.
+    fn chars(&self, tokens: usize) -> usize {
+        (tokens as f32 * self.chars_per_token).floor() as usize
+    }
+
+    /// Fit the summarization of `post` in the context window, given the
+    /// number of tokens of its prompt. Return the posts to summarize in its
+    /// place: the post itself if it fits, or its truncated content or chunks
+    /// depending on the configured overflow handling.
+    pub fn fit_post(&self, post: &models::Post, prompt_tokens: impl Fn(&models::Post) -> usize) -> Result<Vec<models::Post>, Error> {
+        let tokens = prompt_tokens(post);
+
+        let Some(available) = self.available.filter(|&available| tokens > available) else {
+            return Ok(vec![post.clone()])
+        };
+
+        // The room left for the content once the rest of the prompt is in.
+        let overhead = tokens.saturating_sub(self.estimate(&post.content));
+        let max_chars = self.chars(available.saturating_sub(overhead));
+
+        let contents = match self.overflow {
+            _ if max_chars == 0 => return Err(Error::ContextOverflow(tokens, available)),
+            OverflowCfg::Reject => return Err(Error::ContextOverflow(tokens, available)),
+            OverflowCfg::Truncate => {
+                log::warn!("post of about {} tokens truncated to fit the {} tokens of the context window", tokens, available);
+
+                vec![truncate(&post.content, max_chars).trim_end()]
+            },
+            OverflowCfg::Chunk | OverflowCfg::MapReduce => {
+                let max_chars = self.chunk_tokens
+                    .map_or(max_chars, |chunk_tokens| max_chars.min(self.chars(chunk_tokens).max(1)));
+                // The overlap is capped at half of a chunk, so that each chunk
+                // moves forward through the post.
+                let chunks = chunk(&post.content, max_chars, self.chars(self.overlap_tokens).min(max_chars / 2));
+
+                log::info!("post of about {} tokens split into {} chunks to fit the context window", tokens, chunks.len());
+
+                chunks
+            },
+        };
+
+        Ok(contents.into_iter()
+            .map(|content| models::Post {
+                content: String::from(content),
+                ..post.clone()
+            })
+            .collect())
+    }
+
+    /// Split the partial summaries of a post into groups, in order, whose
+    /// merge prompt fits in the context window, given its number of tokens.
+    /// Every group but the last one holds at least two summaries, so that
+    /// merging the groups leaves fewer summaries.
+    pub fn fit_summaries(&self, summaries: &[models::SummarizedInfo], prompt_tokens: impl Fn(&[models::SummarizedInfo]) -> usize) -> Result<Vec<Vec<models::SummarizedInfo>>, Error> {
+        let Some(available) = self.available else {
+            return Ok(vec![summaries.to_vec()])
+        };
+
+        let mut groups = Vec::new();
+        let mut start = 0;
+
+        while start < summaries.len() {
+            let mut end = start + 1;
+
+            while end < summaries.len() && prompt_tokens(&summaries[start..=end]) <= available {
+                end += 1;
+            }
+
+            if end - start == 1 && end < summaries.len() {
+                return Err(Error::ContextOverflow(prompt_tokens(&summaries[start..=end]), available))
+            }
+
+            groups.push(summaries[start..end].to_vec());
+            start = end;
+        }
+
+        Ok(groups)
+    }
+
+    /// Fit the relation prediction of a pair of arguments in the context
+    /// window, given the number of tokens of its prompt, truncating the
+    /// content of both arguments unless overflows are rejected.
+    pub fn fit_arguments(&self, arg_a: &models::Argument, arg_b: &models::Argument, prompt_tokens: impl Fn(&models::Argument, &models::Argument) -> usize) -> Result<(models::Argument, models::Argument), Error> {
+        let tokens = prompt_tokens(arg_a, arg_b);
+
+        let Some(available) = self.available.filter(|&available| tokens > available) else {
+            return Ok((arg_a.clone(), arg_b.clone()))
+        };
+
+        if self.overflow == OverflowCfg::Reject {
+            return Err(Error::ContextOverflow(tokens, available))
+        }
+
+        let overhead = tokens.saturating_sub(self.estimate(&arg_a.raw) + self.estimate(&arg_b.raw));
+        let max_chars = self.chars(available.saturating_sub(overhead) / 2);
+
+        let truncated = |arg: &models::Argument| models::Argument {
+            raw: String::from(truncate(&arg.raw, max_chars)),
+            ..arg.clone()
+        };
+        let (arg_a, arg_b) = (truncated(arg_a), truncated(arg_b));
+
+        // The prompt may not include the content of the arguments at all.
+        let tokens = prompt_tokens(&arg_a, &arg_b);
+
+        if tokens > available {
+            return Err(Error::ContextOverflow(tokens, available))
+        }
+
+        log::warn!(
+            "arguments {:?} and {:?} truncated to fit the {} tokens of the context window",
+            arg_a.id, arg_b.id, available,
+        );
+
+        Ok((arg_a, arg_b))
+    }
+}
+
+/// Cut a text to its first `max_chars` characters.
+fn truncate(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Split a text into chunks of at most `max_chars` characters, each one
+/// starting with about the last `overlap_chars` characters of the previous
+/// one. Chunks are cut at whitespaces when possible so as not to split words.
+fn chunk(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    loop {
+        let rest = &text[start..];
+        let piece = truncate(rest, max_chars);
+
+        let end = if piece.len() == rest.len() {
+            piece.len()
+        } else {
+            truncate(rest, max_chars + 1)
+                .rfind(char::is_whitespace)
+                .map(|index| index + whitespace_len(rest, index))
+                .unwrap_or(piece.len())
+        };
+
+        let chunk = rest[..end].trim();
+
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+
+        if end == rest.len() {
+            return chunks
+        }
+
+        // The next chunk starts at the first word of the overlap, and always
+        // after the start of this chunk.
+        let overlap = rest[..end].chars().count().saturating_sub(overlap_chars);
+        let overlap_start = rest.char_indices().nth(overlap).map_or(end, |(index, _)| index);
+
+        let next = rest[overlap_start..end].find(char::is_whitespace)
+            .map(|index| overlap_start + index + whitespace_len(rest, overlap_start + index))
+            .filter(|&next| next > 0 && overlap_chars > 0)
+            .unwrap_or(end);
+
+        start += next;
+    }
+}
+
+fn whitespace_len(text: &str, index: usize) -> usize {
+    text[index..].chars().next().map_or(0, char::len_utf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(window_tokens: usize, overflow: OverflowCfg) -> Budget {
+        Budget::new(
+            &ContextCfg {
+                window_tokens: Some(window_tokens),
+                response_tokens: 0,
+                chars_per_token: 1.0,
+                overflow,
+                chunk_tokens: None,
+                overlap_tokens: 0,
+            },
+            None,
+        )
+    }
+
+    fn post(content: &str) -> models::Post {
+        models::Post {
+            content: String::from(content),
+            author: None,
+            thread_context: None,
+        }
+    }
+
+    /// A prompt made of a 10 characters instruction followed by the post.
+    fn prompt_tokens(post: &models::Post) -> usize {
+        10 + post.content.chars().count()
+    }
+
+    mod fit_post {
+        use super::*;
+
+        #[test]
+        fn keeps_fitting_posts() {
+            let posts = budget(30, OverflowCfg::Reject)
+                .fit_post(&post("Taxes are too high."), prompt_tokens)
+                .unwrap();
+
+            assert_eq!(posts[0].content, "Taxes are too high.");
+        }
+
+        #[test]
+        fn rejects_overflowing_posts() {
+            assert!(matches!(
+                budget(20, OverflowCfg::Reject).fit_post(&post("Taxes are too high."), prompt_tokens),
+                Err(Error::ContextOverflow(29, 20)),
+            ));
+        }
+
+        #[test]
+        fn truncates_overflowing_posts() {
+            let posts = budget(20, OverflowCfg::Truncate)
+                .fit_post(&post("Taxes are too high."), prompt_tokens)
+                .unwrap();
+
+            assert_eq!(posts.len(), 1);
+            assert_eq!(posts[0].content, "Taxes are");
+        }
+
+        #[test]
+        fn chunks_overflowing_posts() {
+            let posts = budget(20, OverflowCfg::Chunk)
+                .fit_post(&post("Taxes are too high."), prompt_tokens)
+                .unwrap();
+
+            let contents: Vec<&str> = posts.iter().map(|post| post.content.as_str()).collect();
+
+            assert_eq!(contents, ["Taxes are", "too high."]);
+        }
+
+        #[test]
+        fn caps_overlap_below_chunks() {
+            let mut budget = budget(20, OverflowCfg::Chunk);
+            budget.overlap_tokens = 50;
+
+            let posts = budget.fit_post(&post("Taxes are too high."), prompt_tokens)
+                .unwrap();
+
+            let contents: Vec<&str> = posts.iter().map(|post| post.content.as_str()).collect();
+
+            assert_eq!(contents, ["Taxes are", "are too", "too high."]);
+        }
+    }
+
+    mod fit_summaries {
+        use super::*;
+
+        fn summary(title: &str) -> models::SummarizedInfo {
+            models::SummarizedInfo {
+                title: String::from(title),
+                summary: String::new(),
+                quote: None,
+                structure: None,
+            }
+        }
+
+        /// A prompt made of a 10 characters instruction followed by the titles.
+        fn prompt_tokens(summaries: &[models::SummarizedInfo]) -> usize {
+            10 + summaries.iter().map(|summary| summary.title.len()).sum::<usize>()
+        }
+
+        #[test]
+        fn groups_fitting_summaries() {
+            let groups = budget(20, OverflowCfg::MapReduce)
+                .fit_summaries(&[summary("Taxes"), summary("Debt"), summary("Jobs"), summary("Pay"), summary("Aid")], prompt_tokens)
+                .unwrap();
+
+            let titles: Vec<Vec<&str>> = groups.iter()
+                .map(|group| group.iter().map(|summary| summary.title.as_str()).collect())
+                .collect();
+
+            assert_eq!(titles, [vec!["Taxes", "Debt"], vec!["Jobs", "Pay", "Aid"]]);
+        }
+
+        #[test]
+        fn rejects_summaries_that_cannot_be_paired() {
+            assert!(matches!(
+                budget(20, OverflowCfg::MapReduce).fit_summaries(&[summary("Spending"), summary("Jobs")], prompt_tokens),
+                Err(Error::ContextOverflow(22, 20)),
+            ));
+        }
+    }
+
+    mod chunk {
+        use super::*;
+
+        #[test]
+        fn splits_at_whitespaces() {
+            assert_eq!(chunk("Taxes are too high.", 12, 0), ["Taxes are", "too high."]);
+        }
+
+        #[test]
+        fn repeats_overlap() {
+            assert_eq!(
+                chunk("Taxes are too high for most households.", 20, 8),
+                ["Taxes are too high", "high for most", "most households."],
+            );
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::clients::llm::parsing;
+use crate::models;
+
+/// The author of a message of a conversation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+/// Describe a single message of a conversation with a model.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn system(content: String) -> Self {
+        Message { role: Role::System, content }
+    }
+
+    pub fn user(content: String) -> Self {
+        Message { role: Role::User, content }
+    }
+
+    pub fn assistant(content: String) -> Self {
+        Message { role: Role::Assistant, content }
+    }
+}
+
+/// Build the conversation of a prompt: the system prompt, the few-shot
+/// demonstrations as previous turns, the history of the conversation the
+/// prompt continues, then the prompt itself.
+pub fn conversation(system: Option<&str>, demonstrations: &[parsing::Demonstration], history: &[Message], prompt: String) -> Vec<Message> {
+    system.map(|system| Message::system(String::from(system))).into_iter()
+        .chain(demonstrations.iter().flat_map(|demonstration| [
+            Message::user(demonstration.input.clone()),
+            Message::assistant(demonstration.answer.clone()),
+        ]))
+        .chain(history.iter().cloned())
+        .chain(std::iter::once(Message::user(prompt)))
+        .collect()
+}
+
+/// Write the history of a conversation as text, for the endpoints taking a
+/// single prompt.
+pub fn transcript(history: &[Message]) -> String {
+    history.iter()
+        .map(|message| match message.role {
+            Role::System => format!("System: {}", message.content),
+            Role::User => format!("User: {}", message.content),
+            Role::Assistant => format!("Assistant: {}", message.content),
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// A predicted relation, along with the turns of the conversation in which
+/// it was predicted, to continue it with another prompt.
+#[derive(Clone)]
+pub struct Prediction {
+    pub relation: models::Relation,
+    /// The messages sent to the model, without the system prompt, followed by
+    /// the raw response of the model.
+    pub turns: Vec<Message>,
+}
+
+/// Return the turns of a conversation without its system prompt, followed by
+/// the response of the model.
+pub fn turns(messages: &[Message], response: &str) -> Vec<Message> {
+    messages.iter()
+        .filter(|message| message.role != Role::System)
+        .cloned()
+        .chain(std::iter::once(Message::assistant(String::from(response))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod conversation {
+        use super::*;
+
+        #[test]
+        fn turns_demonstrations_into_previous_turns() {
+            let messages = conversation(
+                Some("You analyze arguments."),
+                &[parsing::Demonstration {
+                    input: String::from("Arg:Taxes are too high."),
+                    answer: String::from("Title: Taxes\nSummary: Taxes should be lower."),
+                }],
+                &[],
+                String::from("Arg:We should cut spending."),
+            );
+
+            let roles: Vec<&Role> = messages.iter().map(|message| &message.role).collect();
+
+            assert_eq!(roles, [&Role::System, &Role::User, &Role::Assistant, &Role::User]);
+            assert_eq!(messages[3].content, "Arg:We should cut spending.");
+        }
+    }
+}
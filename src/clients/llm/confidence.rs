@@ -0,0 +1,66 @@
+use std::ops::Range;
+use serde::Deserialize;
+
+/// Describe the log-probability of a generated token, as exposed by backends
+/// supporting it.
+#[derive(Deserialize, Clone)]
+pub struct TokenLogProb {
+    /// The text of the token.
+    pub token: String,
+    /// The natural logarithm of the probability of the token.
+    pub logprob: f64,
+}
+
+/// Compute the joint probability of the tokens which generated the `span`
+/// bytes of a response, the response being the concatenation of the tokens.
+///
+/// Returns `None` if no token overlaps the span.
+pub fn span_probability(tokens: &[TokenLogProb], span: Range<usize>) -> Option<f32> {
+    let mut offset = 0;
+    let mut logprob = None;
+
+    for token in tokens {
+        let end = offset + token.token.len();
+
+        if end > span.start && offset < span.end {
+            logprob = Some(logprob.unwrap_or(0.0) + token.logprob);
+        }
+        if end >= span.end {
+            break;
+        }
+        offset = end;
+    }
+
+    logprob.map(|logprob: f64| logprob.exp() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(tokens: &[(&str, f64)]) -> Vec<TokenLogProb> {
+        tokens.iter()
+            .map(|(token, logprob)| TokenLogProb { token: String::from(*token), logprob: *logprob })
+            .collect()
+    }
+
+    mod span_probability {
+        use super::*;
+
+        #[test]
+        fn multiplies_overlapping_tokens() {
+            let tokens = tokens(&[("Relation", -0.1), (": ", -0.1), ("Sup", 0.5_f64.ln()), ("port", 0.5_f64.ln())]);
+
+            let probability = span_probability(&tokens, 10..17).unwrap();
+
+            assert!((probability - 0.25).abs() < 1e-6);
+        }
+
+        #[test]
+        fn is_none_outside_of_tokens() {
+            let tokens = tokens(&[("Relation", -0.1)]);
+
+            assert!(span_probability(&tokens, 10..17).is_none());
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Mutex;
+use thiserror::Error as ThisError;
+use tokio::fs;
+
+use crate::clients::llm::{self, ClientTrait};
+use crate::configuration::settings::{ExamplesCfg, ExampleSelectionCfg, LLMCfg};
+use crate::models;
+use crate::clients::llm::similarity::cosine_similarity;
+
+#[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("io error: {0}")]
+    IOError(#[from] std::io::Error),
+
+    #[error("serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+
+    #[error("llm client error: {0}")]
+    LLMError(#[from] llm::Error),
+}
+
+/// Load the library of labeled examples of the configuration, or an empty one
+/// if none is configured.
+pub async fn load_examples(cfg: Option<&ExamplesCfg>) -> Result<models::Examples, Error> {
+    let Some(cfg) = cfg else {
+        return Ok(models::Examples::default())
+    };
+
+    let data = fs::read_to_string(&cfg.file).await?;
+
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// An example which can be compared to the input of a prompt.
+pub trait Example: Clone {
+    /// The text embedded to compare the example with the `MostSimilar`
+    /// selection.
+    fn text(&self) -> String;
+}
+
+impl Example for models::SummarizeExample {
+    fn text(&self) -> String {
+        self.content.clone()
+    }
+}
+
+impl Example for models::PredictExample {
+    fn text(&self) -> String {
+        format!("{}\n{}", self.arg_a, self.arg_b)
+    }
+}
+
+/// Select the few-shot demonstrations of each prompt from a library of
+/// examples.
+pub struct FewShot<T> {
+    examples: Vec<T>,
+    count: usize,
+    selection: ExampleSelectionCfg,
+    /// The client embedding the examples and the inputs, only set with the
+    /// `MostSimilar` selection.
+    embedder: Option<llm::Client>,
+    embeddings: Vec<Vec<f32>>,
+    rng: Mutex<fastrand::Rng>,
+}
+
+impl<T: Example> FewShot<T> {
+    /// Create the selector of the configured examples, embedding them with the
+    /// client of `llm_cfg` if the `MostSimilar` selection is used. Without
+    /// configuration no example is ever selected.
+    pub async fn new(examples: Vec<T>, cfg: Option<&ExamplesCfg>, llm_cfg: &LLMCfg) -> Result<Self, Error> {
+        let Some(cfg) = cfg else {
+            return Ok(FewShot {
+                examples: Vec::new(),
+                count: 0,
+                selection: ExampleSelectionCfg::Fixed,
+                embedder: None,
+                embeddings: Vec::new(),
+                rng: Mutex::new(fastrand::Rng::new()),
+            })
+        };
+
+        let embedder = matches!(cfg.selection, ExampleSelectionCfg::MostSimilar)
+            .then(|| llm::Client::new(llm_cfg));
+        let mut embeddings = Vec::new();
+
+        if let Some(ref embedder) = embedder {
+            for example in examples.iter() {
+                embeddings.push(embedder.embed(&example.text()).await?);
+            }
+        }
+
+        Ok(FewShot {
+            examples,
+            count: cfg.count,
+            selection: cfg.selection.clone(),
+            embedder,
+            embeddings,
+            rng: Mutex::new(cfg.seed.map(fastrand::Rng::with_seed).unwrap_or_default()),
+        })
+    }
+
+    /// Select the examples to demonstrate in the prompt of the given input.
+    pub async fn select(&self, input: &str) -> Result<Vec<T>, llm::Error> {
+        if self.examples.is_empty() || self.count == 0 {
+            return Ok(Vec::new())
+        }
+
+        match self.selection {
+            ExampleSelectionCfg::Fixed => Ok(self.examples.iter()
+                .take(self.count)
+                .cloned()
+                .collect()),
+            ExampleSelectionCfg::Random => Ok(self.rng.lock().unwrap()
+                .choose_multiple(self.examples.iter(), self.count)
+                .into_iter()
+                .cloned()
+                .collect()),
+            ExampleSelectionCfg::MostSimilar => {
+                // The embedder is always set with the MostSimilar selection.
+                let input = self.embedder.as_ref().unwrap().embed(input).await?;
+
+                Ok(most_similar(&self.embeddings, &input, self.count).into_iter()
+                    .map(|i| self.examples[i].clone())
+                    .collect())
+            },
+        }
+    }
+}
+
+/// Return the indices of the `count` embeddings most similar to `input`, the
+/// most similar first.
+fn most_similar(embeddings: &[Vec<f32>], input: &[f32], count: usize) -> Vec<usize> {
+    let mut similarities: Vec<(usize, f32)> = embeddings.iter()
+        .map(|embedding| cosine_similarity(embedding, input))
+        .enumerate()
+        .collect();
+
+    similarities.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    similarities.into_iter()
+        .take(count)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod most_similar {
+        use super::*;
+
+        #[test]
+        fn ranks_by_similarity() {
+            let embeddings = vec![
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![0.7, 0.7],
+            ];
+
+            assert_eq!(most_similar(&embeddings, &[1.0, 0.1], 2), vec![1, 2]);
+        }
+    }
+}
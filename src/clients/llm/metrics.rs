@@ -0,0 +1,198 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use serde::Serialize;
+
+/// The usage of a single inference, as reported by the server or measured by
+/// the client.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Usage {
+    /// The number of tokens of the prompt.
+    pub prompt_tokens: u64,
+    /// The number of tokens generated in response.
+    pub completion_tokens: u64,
+    /// The time taken to answer the request.
+    pub latency: Duration,
+    /// The time spent generating the response tokens, if the server reports
+    /// it.
+    pub generation: Option<Duration>,
+}
+
+/// Accumulate the usage of the inferences of a client.
+#[derive(Default)]
+pub struct MetricsCounter {
+    requests: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    latency_ns: AtomicU64,
+    generated_tokens: AtomicU64,
+    generation_ns: AtomicU64,
+}
+
+/// A snapshot of the inference usage of a client.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct InferenceStats {
+    /// The number of inferences answered.
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// The total time taken to answer the inferences.
+    pub latency: Duration,
+    /// The number of response tokens of the inferences whose generation time
+    /// is known.
+    pub generated_tokens: u64,
+    /// The total time spent generating these response tokens.
+    pub generation: Duration,
+}
+
+/// The summary of the inference usage of a run, as printed and written to
+/// the metrics output.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct InferenceSummary {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub mean_latency_ms: f64,
+    pub tokens_per_second: f64,
+}
+
+impl MetricsCounter {
+    /// Record the usage of an answered inference.
+    pub fn record(&self, usage: Usage) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.prompt_tokens.fetch_add(usage.prompt_tokens, Ordering::Relaxed);
+        self.completion_tokens.fetch_add(usage.completion_tokens, Ordering::Relaxed);
+        self.latency_ns.fetch_add(usage.latency.as_nanos() as u64, Ordering::Relaxed);
+
+        if let Some(generation) = usage.generation {
+            self.generated_tokens.fetch_add(usage.completion_tokens, Ordering::Relaxed);
+            self.generation_ns.fetch_add(generation.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> InferenceStats {
+        InferenceStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            prompt_tokens: self.prompt_tokens.load(Ordering::Relaxed),
+            completion_tokens: self.completion_tokens.load(Ordering::Relaxed),
+            latency: Duration::from_nanos(self.latency_ns.load(Ordering::Relaxed)),
+            generated_tokens: self.generated_tokens.load(Ordering::Relaxed),
+            generation: Duration::from_nanos(self.generation_ns.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl InferenceStats {
+    /// The mean time taken to answer an inference, zero if none was.
+    pub fn mean_latency(&self) -> Duration {
+        match self.requests {
+            0 => Duration::ZERO,
+            requests => self.latency / requests as u32,
+        }
+    }
+
+    /// The number of response tokens generated per second of generation,
+    /// over the inferences whose generation time is known, zero if none is.
+    pub fn tokens_per_second(&self) -> f64 {
+        match self.generation.as_secs_f64() {
+            seconds if seconds > 0.0 => self.generated_tokens as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+
+    pub fn summary(&self) -> InferenceSummary {
+        InferenceSummary {
+            requests: self.requests,
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            mean_latency_ms: self.mean_latency().as_secs_f64() * 1000.0,
+            tokens_per_second: self.tokens_per_second(),
+        }
+    }
+}
+
+impl std::iter::Sum for InferenceStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(InferenceStats::default(), |total, stats| InferenceStats {
+            requests: total.requests + stats.requests,
+            prompt_tokens: total.prompt_tokens + stats.prompt_tokens,
+            completion_tokens: total.completion_tokens + stats.completion_tokens,
+            latency: total.latency + stats.latency,
+            generated_tokens: total.generated_tokens + stats.generated_tokens,
+            generation: total.generation + stats.generation,
+        })
+    }
+}
+
+impl fmt::Display for InferenceSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inferences, {} prompt tokens, {} completion tokens, {:.0} ms mean latency, {:.1} tokens/s",
+            self.requests, self.prompt_tokens, self.completion_tokens, self.mean_latency_ms, self.tokens_per_second,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod summary {
+        use super::*;
+
+        #[test]
+        fn averages_latency_and_throughput() {
+            let counter = MetricsCounter::default();
+
+            counter.record(Usage {
+                prompt_tokens: 100,
+                completion_tokens: 20,
+                latency: Duration::from_millis(1000),
+                generation: Some(Duration::from_millis(500)),
+            });
+            counter.record(Usage {
+                prompt_tokens: 50,
+                completion_tokens: 30,
+                latency: Duration::from_millis(2000),
+                generation: Some(Duration::from_millis(500)),
+            });
+
+            assert_eq!(counter.stats().summary(), InferenceSummary {
+                requests: 2,
+                prompt_tokens: 150,
+                completion_tokens: 50,
+                mean_latency_ms: 1500.0,
+                tokens_per_second: 50.0,
+            });
+        }
+
+        #[test]
+        fn ignores_inferences_without_generation_time() {
+            let counter = MetricsCounter::default();
+
+            counter.record(Usage {
+                prompt_tokens: 100,
+                completion_tokens: 20,
+                latency: Duration::from_millis(1000),
+                generation: Some(Duration::from_millis(500)),
+            });
+            counter.record(Usage {
+                prompt_tokens: 50,
+                completion_tokens: 30,
+                latency: Duration::from_millis(2000),
+                generation: None,
+            });
+
+            assert_eq!(counter.stats().summary().tokens_per_second, 40.0);
+        }
+
+        #[test]
+        fn is_zero_without_inference() {
+            let summary = InferenceStats::default().summary();
+
+            assert_eq!(summary.mean_latency_ms, 0.0);
+            assert_eq!(summary.tokens_per_second, 0.0);
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use reqwest;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
-use regex::Regex;
 
 use crate::models::{self, SummarizedInfo};
-use crate::clients::llm;
+use crate::clients::llm::{self, budget, chat, metrics, parsing, parsing::ResponseParser, repair, retry, confidence::TokenLogProb};
 use crate::configuration::settings;
 
 /// Describe a client to a Large Language Model running via an Ollama server.
@@ -13,39 +13,64 @@ pub struct Client {
     client: reqwest::Client,
     /// The name of the Large Language Model request inference from. 
     model: String,
+    /// The name of the model to request embeddings from.
+    embedding_model: String,
     /// The address of Ollama server.
     uri: String,
-    /// The options of the inference.
-    options: Option<ModelOptions>,
-}
-
-/// Describe the configuration of the Ollama client.
-#[derive(Deserialize)]
-struct Configuration {
-
-}
-
-#[derive(Deserialize, Serialize, Clone)]
-struct ModelOptions {
-    /// The temperature of the inference.
-    temperature: f64,
+    /// The options of the summarization inferences.
+    summarize_options: Option<settings::ModelOptions>,
+    /// The options of the relation prediction inferences.
+    predict_options: Option<settings::ModelOptions>,
+    /// Whether responses are constrained by a JSON schema rather than parsed
+    /// from free text.
+    structured_outputs: bool,
+    /// The policy for retrying failed requests.
+    retry: settings::RetryCfg,
+    /// The usage of the inferences.
+    metrics: metrics::MetricsCounter,
+    /// The number of tokens accepted in the summarization prompts.
+    summarize_budget: budget::Budget,
+    /// The number of tokens accepted in the relation prediction prompts.
+    predict_budget: budget::Budget,
 }
 
 #[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("api error: {0}")]
     ApiError(String),
 
+    #[error("server error ({0}): {1}")]
+    ServerError(reqwest::StatusCode, String),
+
     #[error("request error: {0}")]
     RequestError(#[from] reqwest::Error),
 
     #[error("response parsing error: {0}")]
-    ResponseParsingError(String),
+    ResponseParsingError(#[from] parsing::Error),
+}
+
+impl Error {
+    /// Whether the request failing with this error may succeed if sent again,
+    /// as on a timeout, a connection failure or a server-side error.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ServerError(..) => true,
+            Error::RequestError(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            Error::ApiError(_) | Error::ResponseParsingError(_) => false,
+        }
+    }
+
+    /// Whether the response of the request could not be parsed, which another
+    /// inference may fix.
+    pub fn is_parsing(&self) -> bool {
+        matches!(self, Error::ResponseParsingError(_))
+    }
 }
 
 /// Describe the body for a generation http request to a Large Language
 /// Model running via an Ollama server.
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct GenerateRequestBody {
     // the model name.
     model: String,
@@ -56,16 +81,121 @@ struct GenerateRequestBody {
     // additional model parameters listed in the documentation for the Modelfile
     // (https://github.com/ollama/ollama/blob/main/docs/modelfile.md#valid-parameters-and-values)
     // such as temperature
-    options: Option<ModelOptions>,
+    options: Option<settings::ModelOptions>,
+    // the JSON schema the response must conform to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+    // if true the log-probabilities of the generated tokens are returned.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    logprobs: bool,
     // if false the response will be returned as a single response object,
     // rather than a stream of objects.
     stream: bool,
 }
 
+/// Describe the body for a chat http request to a Large Language Model
+/// running via an Ollama server.
+#[derive(Serialize, Clone)]
+struct ChatRequestBody {
+    // the model name.
+    model: String,
+    // the messages of the conversation, the last one being answered.
+    messages: Vec<chat::Message>,
+    // additional model parameters listed in the documentation for the Modelfile.
+    options: Option<settings::ModelOptions>,
+    // the JSON schema the response must conform to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+    // if true the log-probabilities of the generated tokens are returned.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    logprobs: bool,
+    // if false the response will be returned as a single response object,
+    // rather than a stream of objects.
+    stream: bool,
+}
+
+/// Describe an inference request to one of the endpoints of the Ollama
+/// server.
+#[derive(Clone)]
+enum InferenceRequest {
+    Generate(GenerateRequestBody),
+    Chat(ChatRequestBody),
+}
+
+impl InferenceRequest {
+    /// Build the request of a prompt for the given endpoint, from the
+    /// generation request body holding the model parameters, the few-shot
+    /// demonstrations, the history of the conversation the prompt continues
+    /// and the rendering of the prompt from the text of the demonstrations.
+    ///
+    /// The demonstrations and history are written in the prompt for the
+    /// generation endpoint, and sent as previous turns to the chat endpoint.
+    fn new(
+        endpoint: &settings::EndpointCfg,
+        req_body: GenerateRequestBody,
+        demonstrations: &[parsing::Demonstration],
+        history: &[chat::Message],
+        render: impl Fn(&str) -> String,
+    ) -> Self {
+        match endpoint {
+            settings::EndpointCfg::Generate => {
+                let prompt = render(&parsing::render_demonstrations(demonstrations));
+
+                let prompt = match history.is_empty() {
+                    true => prompt,
+                    false => format!("{}\n\n{}", chat::transcript(history), prompt),
+                };
+
+                InferenceRequest::Generate(GenerateRequestBody { prompt, ..req_body })
+            },
+            settings::EndpointCfg::Chat => InferenceRequest::Chat(ChatRequestBody {
+                messages: chat::conversation(req_body.system.as_deref(), demonstrations, history, render("")),
+                model: req_body.model,
+                options: req_body.options,
+                format: req_body.format,
+                logprobs: req_body.logprobs,
+                stream: false,
+            }),
+        }
+    }
+
+    /// Return the turns of the request, without its system prompt, followed by
+    /// the response of the model.
+    fn turns(&self, response: &str) -> Vec<chat::Message> {
+        match self {
+            InferenceRequest::Generate(req_body) => chat::turns(&[chat::Message::user(req_body.prompt.clone())], response),
+            InferenceRequest::Chat(req_body) => chat::turns(&req_body.messages, response),
+        }
+    }
+
+    /// Build the request asking the model to repair its response which could
+    /// not be parsed: the prompt is repeated along with the response for the
+    /// generation endpoint, while the conversation is continued for the chat
+    /// endpoint.
+    fn repaired(&self, response: &str, format: &str, error: &parsing::Error) -> Self {
+        match self {
+            InferenceRequest::Generate(req_body) => InferenceRequest::Generate(GenerateRequestBody {
+                prompt: repair::repair_prompt(&req_body.prompt, response, format, error),
+                ..req_body.clone()
+            }),
+            InferenceRequest::Chat(req_body) => {
+                let mut messages = req_body.messages.clone();
+                messages.push(chat::Message::assistant(String::from(response)));
+                messages.push(chat::Message::user(repair::repair_instruction(format, error)));
+
+                InferenceRequest::Chat(ChatRequestBody {
+                    messages,
+                    ..req_body.clone()
+                })
+            },
+        }
+    }
+}
+
 /// Describe the response body of the Ollama server in case of an error
-/// following an generation http request.
+/// following an http request.
 #[derive(Deserialize)]
-struct GenerateErrorResponseBody {
+struct ErrorResponseBody {
     /// The error message.
     error: String,
 }
@@ -74,155 +204,778 @@ struct GenerateErrorResponseBody {
 /// generation http request.
 #[derive(Deserialize)]
 struct GenerateSuccessResponseBody {
-//    #[allow(dead_code)]
-//    model: String,
-//    #[allow(dead_code)]
-//    created_at: String,
     response: String,
-//    #[allow(dead_code)]
-//    context: Vec<u32>,
-//    #[allow(dead_code)]
-//    total_duration: u64,
-//    #[allow(dead_code)]
-//    load_duration: u64,
-//    #[allow(dead_code)]
-//    prompt_eval_count: u32,
-//    #[allow(dead_code)]
-//    prompt_eval_duration: u64,
-//    #[allow(dead_code)]
-//    eval_count: u32,
-//    #[allow(dead_code)]
-//    eval_duration: u64,
+    /// The log-probabilities of the generated tokens, if requested.
+    #[serde(default)]
+    logprobs: Option<Vec<TokenLogProb>>,
+    #[serde(flatten)]
+    metrics: InferenceMetrics,
+}
+
+/// Describe the metrics of an inference returned along with its response by
+/// the Ollama server, the durations being in nanoseconds.
+#[derive(Deserialize, Default)]
+struct InferenceMetrics {
+    /// The time spent answering the request, model loading included.
+    #[serde(default)]
+    total_duration: u64,
+    /// The number of tokens of the prompt.
+    #[serde(default)]
+    prompt_eval_count: u64,
+    /// The number of tokens of the response.
+    #[serde(default)]
+    eval_count: u64,
+    /// The time spent generating the response, zero if not reported.
+    #[serde(default)]
+    eval_duration: u64,
+}
+
+impl From<&InferenceMetrics> for metrics::Usage {
+    fn from(metrics: &InferenceMetrics) -> Self {
+        metrics::Usage {
+            prompt_tokens: metrics.prompt_eval_count,
+            completion_tokens: metrics.eval_count,
+            latency: std::time::Duration::from_nanos(metrics.total_duration),
+            generation: (metrics.eval_duration > 0)
+                .then(|| std::time::Duration::from_nanos(metrics.eval_duration)),
+        }
+    }
+}
+
+/// Describe a successful response body from the Ollama server following a
+/// chat http request.
+#[derive(Deserialize)]
+struct ChatSuccessResponseBody {
+    /// The message answered by the model.
+    message: chat::Message,
+    /// The log-probabilities of the generated tokens, if requested.
+    #[serde(default)]
+    logprobs: Option<Vec<TokenLogProb>>,
+    #[serde(flatten)]
+    metrics: InferenceMetrics,
+}
+
+impl From<ChatSuccessResponseBody> for GenerateSuccessResponseBody {
+    fn from(body: ChatSuccessResponseBody) -> Self {
+        GenerateSuccessResponseBody {
+            response: body.message.content,
+            logprobs: body.logprobs,
+            metrics: body.metrics,
+        }
+    }
+}
+
+/// Describe the body for an embedding http request to a model running via an
+/// Ollama server.
+#[derive(Serialize)]
+struct EmbedRequestBody {
+    // the model name.
+    model: String,
+    // the text to generate an embedding for.
+    input: String,
+}
+
+/// Describe a successful response body from the Ollama server following an
+/// embedding http request.
+#[derive(Deserialize)]
+struct EmbedSuccessResponseBody {
+    /// The embeddings of the inputs, in order.
+    embeddings: Vec<Vec<f32>>,
+    #[serde(flatten)]
+    metrics: InferenceMetrics,
 }
 
 impl Client {
     pub fn new(cfg: &settings::OllamaCfg) -> Self {
         Client {
-            client: reqwest::Client::new(),
+            client: retry::http_client(&cfg.retry),
             model: cfg.model.clone(),
+            embedding_model: cfg.embedding_model.clone().unwrap_or(cfg.model.clone()),
             uri: cfg.uri.clone(),
-            options: None,
+            summarize_options: task_options(&cfg.options, &cfg.summarize_options),
+            predict_options: task_options(&cfg.options, &cfg.predict_options),
+            structured_outputs: cfg.structured_outputs,
+            retry: cfg.retry.clone(),
+            metrics: metrics::MetricsCounter::default(),
+            summarize_budget: task_budget(&cfg.context, &cfg.options, &cfg.summarize_options),
+            predict_budget: task_budget(&cfg.context, &cfg.options, &cfg.predict_options),
         }
     }
 
-    fn parse_summarize_response(&self, response: String) -> Result<SummarizedInfo, Error> {
-        let re = Regex::new(r"Title: (?<title>.*)\nSummary: (?<summary>.*)").unwrap();
+    pub fn summarize_budget(&self) -> &budget::Budget {
+        &self.summarize_budget
+    }
 
-        let Some(ref caps) = re.captures(&response) else {
-            return Err(Error::ResponseParsingError(String::from("no element found in LLM response")))
-        };
+    pub fn predict_budget(&self) -> &budget::Budget {
+        &self.predict_budget
+    }
 
-        let Some(title) = caps.name("title") else {
-            return Err(Error::ResponseParsingError(String::from("no \"Title\" element found in LLM response")))
-        };
+    /// Whether responses, and the answers of the few-shot demonstrations, are
+    /// written as structured outputs.
+    pub fn structured_outputs(&self) -> bool {
+        self.structured_outputs
+    }
+
+    /// Return the JSON schema to send as the request `format` if structured
+    /// outputs are enabled.
+    fn format(&self, schema: fn() -> serde_json::Value) -> Option<serde_json::Value> {
+        self.structured_outputs.then(schema)
+    }
 
-        let Some(summary) = caps.name("summary") else {
-            return Err(Error::ResponseParsingError(String::from("no \"Summary\" element found in LLM response")))
+    /// Send an inference request to its endpoint of the Ollama server and
+    /// return the successful response body, recording its metrics.
+    async fn generate(&self, request: &InferenceRequest) -> Result<GenerateSuccessResponseBody, Error> {
+        let body: GenerateSuccessResponseBody = match request {
+            InferenceRequest::Generate(req_body) => self.post("/api/generate", req_body).await?,
+            InferenceRequest::Chat(req_body) => self.post::<_, ChatSuccessResponseBody>("/api/chat", req_body).await?
+                .into(),
         };
 
-        Ok(SummarizedInfo {
-            title: String::from(title.as_str()),
-            summary: String::from(summary.as_str())
-        })
-    }
+        self.metrics.record(metrics::Usage::from(&body.metrics));
 
-    fn parse_predict_response(&self, response: String) -> Result<models::RelationType, Error> {
-        let re = Regex::new(r"Relation: (?<relation>.*)").unwrap();
+        Ok(body)
+    }
 
-        let Some(ref caps) = re.captures(&response) else {
-            return Err(Error::ResponseParsingError(String::from("no element found in LLM response")))
-        };
+    /// Send an inference request to the Ollama server and parse its response,
+    /// sending it back to the model for repair while it cannot be parsed and
+    /// repair attempts remain. Return the parsed response along with the last
+    /// request sent, repair turns included.
+    async fn generate_parsed<T>(
+        &self,
+        request: &InferenceRequest,
+        format: &str,
+        parse: impl Fn(&GenerateSuccessResponseBody) -> Result<T, parsing::Error>,
+    ) -> Result<(T, InferenceRequest), Error> {
+        let mut request = request.clone();
+        let mut body = self.generate(&request).await?;
+        let mut attempts = 0;
 
-        let Some(relation_type) = caps.name("relation") else {
-            return Err(Error::ResponseParsingError(String::from("no \"Relation\" element found in LLM response")))
-        };
+        loop {
+            match parse(&body) {
+                Ok(parsed) => return Ok((parsed, request)),
+                Err(e) if attempts < self.retry.repair_attempts => {
+                    log::debug!("failed to parse response, asking the model to repair it: {}", e);
+                    repair::mark_needed();
 
-        Ok(models::RelationType::from(relation_type.as_str()))
+                    request = request.repaired(&body.response, format, &e);
+                    body = self.generate(&request).await?;
+                    attempts += 1;
+                },
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
     }
-}
 
-impl llm::ClientTrait for Client {
-    async fn summarize(&self, prompt: &settings::Prompt, raw: String) -> Result<SummarizedInfo, llm::Error> {
-        let req_body = GenerateRequestBody {
-            model: self.model.clone(),
-            prompt: format!("{}\n\nArg:{}\n", prompt.prompt.clone(), raw),
-            system: prompt.system.clone(),
-            options: self.options.clone(),
-            stream: false,
-        };
+    /// Send a request to the given endpoint of the Ollama server, retrying it
+    /// on transient failures, and return the successful response body.
+    async fn post<B: Serialize, R: DeserializeOwned>(&self, endpoint: &str, req_body: &B) -> Result<R, Error> {
+        retry::retry(&self.retry, self.retry.max_retries, Error::is_retryable, || self.post_once(endpoint, req_body))
+            .await
+    }
 
+    /// Send a request to the given endpoint of the Ollama server and return
+    /// the successful response body.
+    async fn post_once<B: Serialize, R: DeserializeOwned>(&self, endpoint: &str, req_body: &B) -> Result<R, Error> {
         let res: reqwest::Response = self.client
-            .post(format!("{}/api/generate", &self.uri))
-            .json(&req_body)
+            .post(format!("{}{}", &self.uri, endpoint))
+            .json(req_body)
             .send()
-            .await
-            .map_err(Error::from)?;
+            .await?;
+
+        let status = res.status();
 
-        if !res.status().is_success() {
+        if !status.is_success() {
             let body_parsing = res
-                .json::<GenerateErrorResponseBody>()
+                .json::<ErrorResponseBody>()
                 .await;
-            
-            match body_parsing {
-                Ok(body) => Err(llm::Error::Ollama(Error::ApiError(body.error))),
-                Err(e) => Err(llm::Error::Ollama(Error::ApiError(format!("failed to parse response body: {}", e)))),
+
+            let message = match body_parsing {
+                Ok(body) => body.error,
+                Err(e) => format!("failed to parse response body: {}", e),
+            };
+
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                Err(Error::ServerError(status, message))
+            } else {
+                Err(Error::ApiError(message))
             }
         } else {
             let body_parsing = res
-                .json::<GenerateSuccessResponseBody>()
+                .json::<R>()
                 .await;
             
             match body_parsing {
-                Ok(body) => Ok(self.parse_summarize_response(body.response)?),
-                Err(e) => Err(llm::Error::Ollama(Error::ApiError(format!("failed to parse response body: {}", e)))),
+                Ok(body) => Ok(body),
+                Err(e) => Err(Error::ApiError(format!("failed to parse response body: {}", e))),
             }
         }
     }
+}
+
+/// Resolve the options of a task from the shared options and the task
+/// overrides, leaving them to the Modelfile defaults if none is set.
+fn task_options(shared: &settings::ModelOptions, overrides: &settings::ModelOptions) -> Option<settings::ModelOptions> {
+    let options = overrides.clone().merge(shared.clone());
+
+    (!options.is_empty()).then_some(options)
+}
+
+/// Create the budget of a task from the context window of its options. The
+/// window is left unknown if none is set, as it then depends on the Modelfile
+/// and the server version.
+fn task_budget(cfg: &settings::ContextCfg, shared: &settings::ModelOptions, overrides: &settings::ModelOptions) -> budget::Budget {
+    let num_ctx = overrides.num_ctx.or(shared.num_ctx)
+        .map(|num_ctx| num_ctx as usize);
+
+    budget::Budget::new(cfg, num_ctx)
+}
+
+impl llm::ClientTrait for Client {
+    /// Summarize the post at once, the chunks of a post being merged by the
+    /// wrapping client.
+    async fn summarize(&self, prompt: &settings::Prompt, _reduce: Option<&settings::Prompt>, post: &models::Post, examples: &[models::SummarizeExample]) -> Result<Vec<SummarizedInfo>, llm::Error> {
+        let parser = parsing::Parser::new(&prompt.format);
+        let demonstrations = parsing::summarize_demonstrations(examples, self.structured_outputs, &parser);
 
-    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument) -> Result<models::Relation, llm::Error> {
         let req_body = GenerateRequestBody {
             model: self.model.clone(),
-            prompt: format!("{}\n\nArg1:{}\nArg2:{}\n", prompt.prompt.clone(), arg_a.raw, arg_b.raw),
+            prompt: String::new(),
             system: prompt.system.clone(),
-            options: self.options.clone(),
+            options: self.summarize_options.clone(),
+            format: self.format(parsing::schema_for::<parsing::SummarizeResponse>),
+            logprobs: false,
             stream: false,
         };
 
-        let res: reqwest::Response = self.client
-            .post(format!("{}/api/generate", &self.uri))
-            .json(&req_body)
-            .send()
-            .await
-            .map_err(Error::from)?;
+        let request = InferenceRequest::new(&prompt.endpoint, req_body, &demonstrations, &[], |demonstrations| {
+            prompt.render_summarize(post, demonstrations)
+        });
 
-        if !res.status().is_success() {
-            let body_parsing = res
-                .json::<GenerateErrorResponseBody>()
+        let format = parsing::summarize_format(self.structured_outputs, &parser);
+        let (request, parser, format) = (&request, &parser, &format);
+
+        let (arguments, _) = retry::retry(&self.retry, self.retry.parse_retries, Error::is_parsing, || async move {
+            self.generate_parsed(request, format, |body| {
+                parsing::parse_with_fallback(&body.response, self.structured_outputs, |response| {
+                    parser.parse_summarize_response(response).map(parsing::SummarizedArguments)
+                })
+            }).await
+        }).await?;
+
+        Ok(arguments.0)
+    }
+
+    async fn reduce(&self, prompt: &settings::Prompt, post: &models::Post, summaries: &[SummarizedInfo]) -> Result<SummarizedInfo, llm::Error> {
+        let parser = parsing::Parser::new(&prompt.format);
+        let summaries = parser.format_summarize_response(summaries);
+
+        let req_body = GenerateRequestBody {
+            model: self.model.clone(),
+            prompt: String::new(),
+            system: prompt.system.clone(),
+            options: self.summarize_options.clone(),
+            format: self.format(parsing::schema_for::<parsing::SummarizeResponse>),
+            logprobs: false,
+            stream: false,
+        };
+
+        let request = InferenceRequest::new(&prompt.endpoint, req_body, &[], &[], |_| {
+            prompt.render_reduce(post, &summaries)
+        });
+
+        let format = parsing::summarize_format(self.structured_outputs, &parser);
+        let (request, parser, format) = (&request, &parser, &format);
+
+        let (info, _) = retry::retry(&self.retry, self.retry.parse_retries, Error::is_parsing, || async move {
+            self.generate_parsed(request, format, |body| {
+                parsing::parse_with_fallback(&body.response, self.structured_outputs, |response| {
+                    parser.parse_summarize_response(response).map(parsing::SummarizedArguments)
+                }).and_then(parsing::SummarizedArguments::merged)
+            }).await
+        }).await?;
+
+        Ok(info)
+    }
+
+    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], cfg: &settings::PredictionCfg, history: &[chat::Message]) -> Result<chat::Prediction, llm::Error> {
+        let confidence = &cfg.confidence;
+        let parser = parsing::Parser::new(&prompt.format);
+        let demonstrations = parsing::predict_demonstrations(examples, &prompt.arguments, self.structured_outputs, &parser);
+
+        let req_body = GenerateRequestBody {
+            model: self.model.clone(),
+            prompt: String::new(),
+            system: prompt.system.clone(),
+            options: self.predict_options.clone(),
+            format: self.structured_outputs.then(|| parsing::predict_schema(confidence, &cfg.taxonomy)),
+            logprobs: matches!(confidence, settings::ConfidenceCfg::LogProbs),
+            stream: false,
+        };
+
+        let request = InferenceRequest::new(&prompt.endpoint, req_body, &demonstrations, history, |demonstrations| {
+            prompt.render_predict(arg_a, arg_b, demonstrations)
+        });
+
+        let format = parsing::predict_format(self.structured_outputs, &parser, confidence, &cfg.taxonomy);
+        let (request, parser, format) = (&request, &parser, &format);
+
+        let ((prediction, response), request) = retry::retry(&self.retry, self.retry.parse_retries, Error::is_parsing, || async move {
+            self.generate_parsed(request, format, |body| {
+                parsing::parse_prediction(
+                    &body.response,
+                    self.structured_outputs,
+                    parser,
+                    cfg,
+                    body.logprobs.as_deref(),
+                ).map(|prediction| (prediction, body.response.clone()))
+            }).await
+        }).await?;
+
+        Ok(chat::Prediction {
+            relation: models::Relation {
+                arg_a_id: arg_a.id.unwrap(),
+                arg_b_id: arg_b.id.unwrap(),
+                relation_type: cfg.taxonomy.map(prediction.relation_type),
+                confidence: prediction.confidence,
+                explanation: prediction.explanation,
+                votes: Vec::new(),
+                contradicted: false,
+            },
+            turns: request.turns(&response),
+        })
+    }
+
+    async fn embed(&self, content: &str) -> Result<Vec<f32>, llm::Error> {
+        let req_body = EmbedRequestBody {
+            model: self.embedding_model.clone(),
+            input: String::from(content),
+        };
+
+        let body: EmbedSuccessResponseBody = self.post("/api/embed", &req_body).await?;
+
+        self.metrics.record(metrics::Usage::from(&body.metrics));
+
+        Ok(body.embeddings.into_iter()
+            .next()
+            .ok_or(Error::ApiError(String::from("no embedding found in response body")))?)
+    }
+
+    fn inference_stats(&self) -> metrics::InferenceStats {
+        self.metrics.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::llm::ClientTrait;
+
+    fn client(uri: String, structured_outputs: bool) -> Client {
+        Client::new(&settings::OllamaCfg {
+            uri,
+            model: String::from("test-model"),
+            embedding_model: None,
+            structured_outputs,
+            options: settings::ModelOptions {
+                temperature: Some(0.8),
+                ..Default::default()
+            },
+            summarize_options: settings::ModelOptions {
+                seed: Some(42),
+                ..Default::default()
+            },
+            predict_options: settings::ModelOptions::default(),
+            retry: settings::RetryCfg {
+                max_retries: 1,
+                initial_backoff_ms: 0,
+                ..Default::default()
+            },
+            context: settings::ContextCfg::default(),
+        })
+    }
+
+    fn post(content: &str) -> models::Post {
+        models::Post {
+            content: String::from(content),
+            author: None,
+            thread_context: None,
+        }
+    }
+
+    fn prompt() -> settings::Prompt {
+        settings::Prompt {
+            system: None,
+            prompt: String::from("Summarize the argument."),
+            format: settings::ResponseFormatCfg::KeyValue,
+            arguments: settings::ArgumentFieldsCfg::Raw,
+            endpoint: settings::EndpointCfg::Generate,
+        }
+    }
+
+    fn generation(response: &str) -> String {
+        serde_json::json!({
+            "model": "test-model",
+            "response": response,
+            "done": true,
+        }).to_string()
+    }
+
+    mod summarize {
+        use super::*;
+
+        #[tokio::test]
+        async fn sends_schema_and_parses_structured_output() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/api/generate")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "options": { "temperature": 0.8, "seed": 42 },
+                    "format": parsing::schema_for::<parsing::SummarizeResponse>(),
+                })))
+                .with_header("content-type", "application/json")
+                .with_body(generation(r#"{"arguments": [{"title": "Taxes", "summary": "Taxes should be lower."}, {"title": "Spending", "summary": "Spending should be cut."}]}"#))
+                .create_async()
                 .await;
-            
-            match body_parsing {
-                Ok(body) => Err(llm::Error::Ollama(Error::ApiError(body.error))),
-                Err(e) => Err(llm::Error::Ollama(Error::ApiError(format!("failed to parse response body: {}", e)))),
-            }
-        } else {
-            let body_parsing = res
-                .json::<GenerateSuccessResponseBody>()
+
+            let info = client(server.url(), true)
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(info.len(), 2);
+            assert_eq!(info[0].title, "Taxes");
+            assert_eq!(info[0].summary, "Taxes should be lower.");
+            assert_eq!(info[1].title, "Spending");
+        }
+
+        #[tokio::test]
+        async fn falls_back_to_free_text() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Taxes should be lower."))
+                .create_async()
                 .await;
-            
-            match body_parsing {
-                Ok(body) => {
-                    let relation_type = self.parse_predict_response(body.response)?;
-
-                    Ok(models::Relation {
-                        arg_a_id: arg_a.id.unwrap(),
-                        arg_b_id: arg_b.id.unwrap(),
-                        relation_type: relation_type,
-                        confidence: 1.0_f32,
-                        explanation: String::from(""),
-                    })
-                },
-                Err(e) => Err(llm::Error::Ollama(Error::ApiError(format!("failed to parse response body: {}", e)))),
+
+            let info = client(server.url(), true)
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            assert_eq!(info[0].title, "Taxes");
+        }
+
+        #[tokio::test]
+        async fn retries_server_errors() {
+            let mut server = mockito::Server::new_async().await;
+            let failure = server.mock("POST", "/api/generate")
+                .with_status(503)
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"error": "server busy"}"#)
+                .expect(1)
+                .create_async()
+                .await;
+            server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Taxes should be lower."))
+                .create_async()
+                .await;
+
+            let info = client(server.url(), true)
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            failure.assert_async().await;
+            assert_eq!(info[0].title, "Taxes");
+        }
+
+        #[tokio::test]
+        async fn does_not_retry_client_errors() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/api/generate")
+                .with_status(404)
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"error": "model not found"}"#)
+                .expect(1)
+                .create_async()
+                .await;
+
+            let result = client(server.url(), true)
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await;
+
+            mock.assert_async().await;
+            assert!(matches!(result, Err(llm::Error::Ollama(Error::ApiError(_)))));
+        }
+
+        #[tokio::test]
+        async fn retries_unparsable_responses() {
+            let mut server = mockito::Server::new_async().await;
+            let failure = server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(generation("I cannot summarize this."))
+                .expect(1)
+                .create_async()
+                .await;
+            server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Taxes should be lower."))
+                .create_async()
+                .await;
+
+            let info = client(server.url(), true)
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            failure.assert_async().await;
+            assert_eq!(info[0].title, "Taxes");
+        }
+    }
+
+    mod generate_parsed {
+        use super::*;
+
+        #[tokio::test]
+        async fn repairs_unparsable_responses() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(generation("I cannot summarize this."))
+                .create_async()
+                .await;
+            let repair = server.mock("POST", "/api/generate")
+                .match_body(mockito::Matcher::Regex(String::from("Your previous answer was")))
+                .with_header("content-type", "application/json")
+                .with_body(generation("Title: Taxes\nSummary: Taxes should be lower."))
+                .create_async()
+                .await;
+
+            let mut client = client(server.url(), false);
+            client.retry.parse_retries = 0;
+            client.retry.repair_attempts = 1;
+
+            let repairs = repair::RepairCounter::default();
+
+            let info = repairs.track(client.summarize(&prompt(), None, &post("Taxes are too high."), &[]))
+                .await
+                .unwrap();
+
+            repair.assert_async().await;
+            assert_eq!(info[0].title, "Taxes");
+            assert_eq!(repairs.stats(), repair::RepairStats { needed: 1, repaired: 1 });
+        }
+    }
+
+    mod chat {
+        use super::*;
+
+        fn chat_prompt() -> settings::Prompt {
+            settings::Prompt {
+                system: Some(String::from("You are an argument mining assistant.")),
+                endpoint: settings::EndpointCfg::Chat,
+                ..prompt()
             }
         }
+
+        fn answer(content: &str) -> String {
+            serde_json::json!({
+                "model": "test-model",
+                "message": { "role": "assistant", "content": content },
+                "done": true,
+            }).to_string()
+        }
+
+        #[tokio::test]
+        async fn sends_demonstrations_as_turns() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/api/chat")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "messages": [
+                        { "role": "system", "content": "You are an argument mining assistant." },
+                        { "role": "user", "content": "Arg:Taxes are too high." },
+                        { "role": "assistant", "content": "Title: Taxes\nSummary: Taxes should be lower." },
+                        { "role": "user", "content": "Summarize the argument.\n\nArg:We should cut spending.\n" },
+                    ],
+                })))
+                .with_header("content-type", "application/json")
+                .with_body(answer("Title: Spending\nSummary: Spending should be cut."))
+                .create_async()
+                .await;
+
+            let example = models::SummarizeExample {
+                content: String::from("Taxes are too high."),
+                title: String::from("Taxes"),
+                summary: String::from("Taxes should be lower."),
+                quote: None,
+                structure: None,
+            };
+
+            let info = client(server.url(), false)
+                .summarize(&chat_prompt(), None, &post("We should cut spending."), &[example])
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(info[0].title, "Spending");
+        }
+
+        #[tokio::test]
+        async fn continues_conversation_for_repair() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/api/chat")
+                .with_header("content-type", "application/json")
+                .with_body(answer("I cannot summarize this."))
+                .create_async()
+                .await;
+            let repair = server.mock("POST", "/api/chat")
+                .match_body(mockito::Matcher::Regex(String::from(
+                    r#""role":"assistant","content":"I cannot summarize this."\},\{"role":"user","content":"It could not be read"#,
+                )))
+                .with_header("content-type", "application/json")
+                .with_body(answer("Title: Taxes\nSummary: Taxes should be lower."))
+                .create_async()
+                .await;
+
+            let mut client = client(server.url(), false);
+            client.retry.parse_retries = 0;
+            client.retry.repair_attempts = 1;
+
+            let info = client
+                .summarize(&chat_prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            repair.assert_async().await;
+            assert_eq!(info[0].title, "Taxes");
+        }
+
+        #[tokio::test]
+        async fn keeps_repair_turns_of_prediction() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/api/chat")
+                .with_header("content-type", "application/json")
+                .with_body(answer("I am not sure."))
+                .create_async()
+                .await;
+            server.mock("POST", "/api/chat")
+                .match_body(mockito::Matcher::Regex(String::from(
+                    r#""role":"assistant","content":"I am not sure."\},\{"role":"user","content":"It could not be read"#,
+                )))
+                .with_header("content-type", "application/json")
+                .with_body(answer("Relation: Support"))
+                .create_async()
+                .await;
+
+            let mut client = client(server.url(), false);
+            client.retry.parse_retries = 0;
+            client.retry.repair_attempts = 1;
+
+            let info = SummarizedInfo { title: String::new(), summary: String::new(), quote: None, structure: None };
+            let arg_a = models::Argument::with_id(1, info.clone(), String::from("A"), None);
+            let arg_b = models::Argument::with_id(2, info, String::from("B"), None);
+
+            let prediction = client
+                .predict(&chat_prompt(), &arg_a, &arg_b, &[], &settings::PredictionCfg::default(), &[])
+                .await
+                .unwrap();
+
+            assert_eq!(prediction.turns.len(), 4);
+            assert_eq!(prediction.turns[1], llm::chat::Message::assistant(String::from("I am not sure.")));
+            assert_eq!(prediction.turns[3], llm::chat::Message::assistant(String::from("Relation: Support")));
+        }
+    }
+
+    mod inference_stats {
+        use super::*;
+
+        #[tokio::test]
+        async fn records_response_metrics() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::json!({
+                    "model": "test-model",
+                    "response": "Title: Taxes\nSummary: Taxes should be lower.",
+                    "done": true,
+                    "total_duration": 2_000_000_000_u64,
+                    "load_duration": 500_000_000_u64,
+                    "prompt_eval_count": 120,
+                    "prompt_eval_duration": 300_000_000_u64,
+                    "eval_count": 40,
+                    "eval_duration": 1_000_000_000_u64,
+                }).to_string())
+                .create_async()
+                .await;
+
+            let client = client(server.url(), false);
+
+            client.summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            assert_eq!(client.inference_stats(), metrics::InferenceStats {
+                requests: 1,
+                prompt_tokens: 120,
+                completion_tokens: 40,
+                latency: std::time::Duration::from_secs(2),
+                generated_tokens: 40,
+                generation: std::time::Duration::from_secs(1),
+            });
+        }
+    }
+
+    mod embed {
+        use super::*;
+
+        #[tokio::test]
+        async fn returns_first_embedding() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/api/embed")
+                .match_body(mockito::Matcher::Json(serde_json::json!({
+                    "model": "test-model",
+                    "input": "Taxes should be lower.",
+                })))
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"model": "test-model", "embeddings": [[0.5, -0.5]]}"#)
+                .create_async()
+                .await;
+
+            let embedding = client(server.url(), true)
+                .embed("Taxes should be lower.")
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(embedding, vec![0.5, -0.5]);
+        }
+    }
+
+    mod task_budget {
+        use super::*;
+
+        fn fit(shared: Option<u32>, overrides: Option<u32>, content_len: usize) -> Result<Vec<models::Post>, budget::Error> {
+            let cfg = settings::ContextCfg {
+                response_tokens: 0,
+                chars_per_token: 1.0,
+                ..Default::default()
+            };
+            let options = |num_ctx| settings::ModelOptions { num_ctx, ..Default::default() };
+
+            task_budget(&cfg, &options(shared), &options(overrides))
+                .fit_post(&post(&"a".repeat(content_len)), |post| post.content.len())
+        }
+
+        #[test]
+        fn uses_task_context_window() {
+            assert!(fit(Some(4096), None, 3000).is_ok());
+            assert!(matches!(fit(Some(4096), Some(1024), 3000), Err(budget::Error::ContextOverflow(3000, 1024))));
+        }
+
+        #[test]
+        fn disables_guard_without_context_window() {
+            assert!(fit(None, None, 100_000).is_ok());
+        }
     }
 }
@@ -0,0 +1,676 @@
+use reqwest;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+
+use crate::models::{self, SummarizedInfo};
+use crate::clients::llm::{self, budget, chat, metrics, parsing, parsing::ResponseParser, repair, retry, confidence::TokenLogProb};
+use crate::configuration::settings;
+
+/// Describe a client to a Large Language Model served behind an
+/// OpenAI-compatible chat completions API (e.g., vLLM, llama.cpp server).
+pub struct Client {
+    /// The underlying http client.
+    client: reqwest::Client,
+    /// The name of the Large Language Model request inference from.
+    model: String,
+    /// The name of the model to request embeddings from.
+    embedding_model: String,
+    /// The base address of the server, without the `/v1` suffix.
+    uri: String,
+    /// The optional key sent as a bearer token to the server.
+    api_key: Option<String>,
+    /// The sampling parameters of the summarization inferences.
+    summarize_options: SamplingOptions,
+    /// The sampling parameters of the relation prediction inferences.
+    predict_options: SamplingOptions,
+    /// The policy for retrying failed requests.
+    retry: settings::RetryCfg,
+    /// The usage of the inferences.
+    metrics: metrics::MetricsCounter,
+    /// The number of tokens accepted in the prompts.
+    budget: budget::Budget,
+}
+
+#[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("api error: {0}")]
+    ApiError(String),
+
+    #[error("server error ({0}): {1}")]
+    ServerError(reqwest::StatusCode, String),
+
+    #[error("request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+
+    #[error("response parsing error: {0}")]
+    ResponseParsingError(#[from] parsing::Error),
+}
+
+impl Error {
+    /// Whether the request failing with this error may succeed if sent again,
+    /// as on a timeout, a connection failure or a server-side error.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ServerError(..) => true,
+            Error::RequestError(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            Error::ApiError(_) | Error::ResponseParsingError(_) => false,
+        }
+    }
+
+    /// Whether the response of the request could not be parsed, which another
+    /// inference may fix.
+    pub fn is_parsing(&self) -> bool {
+        matches!(self, Error::ResponseParsingError(_))
+    }
+}
+
+/// Describe the body for a chat completion http request to an
+/// OpenAI-compatible server.
+#[derive(Serialize, Clone)]
+struct ChatCompletionRequestBody {
+    // the model name.
+    model: String,
+    // the messages of the conversation so far.
+    messages: Vec<chat::Message>,
+    // the sampling parameters of the inference.
+    #[serde(flatten)]
+    options: SamplingOptions,
+    // if true the log-probabilities of the generated tokens are returned.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    logprobs: bool,
+    // if false the response will be returned as a single response object,
+    // rather than a stream of objects.
+    stream: bool,
+}
+
+/// Describe the sampling parameters of a chat completion request, unset ones
+/// being left to the server defaults.
+#[derive(Serialize, Clone, Default, Debug, PartialEq)]
+struct SamplingOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+}
+
+impl From<settings::ModelOptions> for SamplingOptions {
+    /// Keep the inference options supported by the chat completions API,
+    /// warning about the others.
+    fn from(options: settings::ModelOptions) -> Self {
+        if options.top_k.is_some() || options.num_ctx.is_some() || options.repeat_penalty.is_some() {
+            log::warn!("top_k, num_ctx and repeat_penalty options are not supported by OpenAI-compatible servers, ignoring them");
+        }
+
+        SamplingOptions {
+            temperature: options.temperature,
+            top_p: options.top_p,
+            seed: options.seed,
+            // A negative number of tokens to generate means no limit.
+            max_tokens: options.num_predict.and_then(|num_predict| u32::try_from(num_predict).ok()),
+            stop: options.stop,
+        }
+    }
+}
+
+/// Describe the response body of an OpenAI-compatible server in case of an
+/// error following an http request.
+#[derive(Deserialize)]
+struct ErrorResponseBody {
+    error: ApiErrorDetails,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetails {
+    /// The error message.
+    message: String,
+}
+
+/// Describe a successful response body from an OpenAI-compatible server
+/// following a chat completion http request.
+#[derive(Deserialize)]
+struct ChatCompletionSuccessResponseBody {
+    choices: Vec<Choice>,
+    /// The token counts of the completion, if reported by the server.
+    #[serde(default)]
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct CompletionUsage {
+    prompt_tokens: u64,
+    /// The number of tokens of the response, not reported for embeddings.
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: chat::Message,
+    /// The log-probabilities of the generated tokens, if requested.
+    #[serde(default)]
+    logprobs: Option<ChoiceLogProbs>,
+}
+
+#[derive(Deserialize)]
+struct ChoiceLogProbs {
+    content: Option<Vec<TokenLogProb>>,
+}
+
+/// Describe the body for an embedding http request to an OpenAI-compatible
+/// server.
+#[derive(Serialize)]
+struct EmbeddingRequestBody {
+    // the model name.
+    model: String,
+    // the text to generate an embedding for.
+    input: String,
+}
+
+/// Describe a successful response body from an OpenAI-compatible server
+/// following an embedding http request.
+#[derive(Deserialize)]
+struct EmbeddingSuccessResponseBody {
+    data: Vec<Embedding>,
+    /// The token counts of the inputs, if reported by the server.
+    #[serde(default)]
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    embedding: Vec<f32>,
+}
+
+impl Client {
+    pub fn new(cfg: &settings::OpenAICompatibleCfg) -> Self {
+        Client {
+            client: retry::http_client(&cfg.retry),
+            model: cfg.model.clone(),
+            embedding_model: cfg.embedding_model.clone().unwrap_or(cfg.model.clone()),
+            uri: cfg.uri.trim_end_matches('/').to_string(),
+            api_key: cfg.api_key.clone(),
+            summarize_options: SamplingOptions::from(cfg.summarize_options.clone().merge(cfg.options.clone())),
+            predict_options: SamplingOptions::from(cfg.predict_options.clone().merge(cfg.options.clone())),
+            retry: cfg.retry.clone(),
+            metrics: metrics::MetricsCounter::default(),
+            budget: budget::Budget::new(&cfg.context, None),
+        }
+    }
+
+    pub fn summarize_budget(&self) -> &budget::Budget {
+        &self.budget
+    }
+
+    pub fn predict_budget(&self) -> &budget::Budget {
+        &self.budget
+    }
+
+    /// Build the request of a prompt continuing a conversation.
+    fn request(&self, system: Option<&str>, history: &[chat::Message], prompt: String, options: &SamplingOptions, logprobs: bool) -> ChatCompletionRequestBody {
+        ChatCompletionRequestBody {
+            model: self.model.clone(),
+            messages: chat::conversation(system, &[], history, prompt),
+            options: options.clone(),
+            logprobs,
+            stream: false,
+        }
+    }
+
+    /// Send a request to the chat completions endpoint and return the first
+    /// choice.
+    async fn complete(&self, req_body: &ChatCompletionRequestBody) -> Result<Choice, Error> {
+        let start = std::time::Instant::now();
+        let body: ChatCompletionSuccessResponseBody = self.post("/v1/chat/completions", req_body).await?;
+        let latency = start.elapsed();
+
+        // The server does not report its generation time, left out of the
+        // throughput.
+        self.metrics.record(metrics::Usage {
+            prompt_tokens: body.usage.as_ref().map_or(0, |usage| usage.prompt_tokens),
+            completion_tokens: body.usage.as_ref().map_or(0, |usage| usage.completion_tokens),
+            latency,
+            generation: None,
+        });
+
+        body.choices.into_iter()
+            .next()
+            .ok_or(Error::ApiError(String::from("no choice found in response body")))
+    }
+
+    /// Send a request to the chat completions endpoint and parse the first
+    /// choice, sending it back to the model for repair while it cannot be
+    /// parsed and repair attempts remain. Return the parsed choice along with
+    /// the messages of the last request sent, repair turns included.
+    async fn complete_parsed<T>(
+        &self,
+        req_body: &ChatCompletionRequestBody,
+        format: &str,
+        parse: impl Fn(&Choice) -> Result<T, parsing::Error>,
+    ) -> Result<(T, Vec<chat::Message>), Error> {
+        let mut req_body = req_body.clone();
+        let mut choice = self.complete(&req_body).await?;
+        let mut attempts = 0;
+
+        loop {
+            match parse(&choice) {
+                Ok(parsed) => return Ok((parsed, req_body.messages)),
+                Err(e) if attempts < self.retry.repair_attempts => {
+                    log::debug!("failed to parse response, asking the model to repair it: {}", e);
+                    repair::mark_needed();
+
+                    // The conversation is continued with the response and the
+                    // instruction to repair it.
+                    req_body.messages.push(chat::Message::assistant(choice.message.content.clone()));
+                    req_body.messages.push(chat::Message::user(repair::repair_instruction(format, &e)));
+
+                    choice = self.complete(&req_body).await?;
+                    attempts += 1;
+                },
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Send a request to the given endpoint of the server, retrying it on
+    /// transient failures, and return the successful response body.
+    async fn post<B: Serialize, R: DeserializeOwned>(&self, endpoint: &str, req_body: &B) -> Result<R, Error> {
+        retry::retry(&self.retry, self.retry.max_retries, Error::is_retryable, || self.post_once(endpoint, req_body))
+            .await
+    }
+
+    /// Send a request to the given endpoint of the server and return the
+    /// successful response body.
+    async fn post_once<B: Serialize, R: DeserializeOwned>(&self, endpoint: &str, req_body: &B) -> Result<R, Error> {
+        let mut req = self.client
+            .post(format!("{}{}", &self.uri, endpoint))
+            .json(req_body);
+
+        if let Some(ref api_key) = self.api_key {
+            req = req.bearer_auth(api_key);
+        }
+
+        let res: reqwest::Response = req.send().await?;
+
+        let status = res.status();
+
+        if !status.is_success() {
+            let body_parsing = res
+                .json::<ErrorResponseBody>()
+                .await;
+
+            let message = match body_parsing {
+                Ok(body) => body.error.message,
+                Err(e) => format!("failed to parse response body: {}", e),
+            };
+
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                Err(Error::ServerError(status, message))
+            } else {
+                Err(Error::ApiError(message))
+            }
+        } else {
+            let body_parsing = res
+                .json::<R>()
+                .await;
+
+            match body_parsing {
+                Ok(body) => Ok(body),
+                Err(e) => Err(Error::ApiError(format!("failed to parse response body: {}", e))),
+            }
+        }
+    }
+}
+
+impl llm::ClientTrait for Client {
+    /// Summarize the post at once, the chunks of a post being merged by the
+    /// wrapping client.
+    async fn summarize(&self, prompt: &settings::Prompt, _reduce: Option<&settings::Prompt>, post: &models::Post, examples: &[models::SummarizeExample]) -> Result<Vec<SummarizedInfo>, llm::Error> {
+        let parser = parsing::Parser::new(&prompt.format);
+        let content = prompt.render_summarize(post, &parsing::render_demonstrations(&parsing::summarize_demonstrations(examples, false, &parser)));
+        let format = parsing::summarize_format(false, &parser);
+        let req_body = self.request(prompt.system.as_deref(), &[], content, &self.summarize_options, false);
+
+        let (infos, _) = retry::retry(&self.retry, self.retry.parse_retries, Error::is_parsing, || {
+            self.complete_parsed(&req_body, &format, |choice| {
+                parser.parse_summarize_response(&choice.message.content)
+            })
+        }).await?;
+
+        Ok(infos)
+    }
+
+    async fn reduce(&self, prompt: &settings::Prompt, post: &models::Post, summaries: &[SummarizedInfo]) -> Result<SummarizedInfo, llm::Error> {
+        let parser = parsing::Parser::new(&prompt.format);
+        let content = prompt.render_reduce(post, &parser.format_summarize_response(summaries));
+        let format = parsing::summarize_format(false, &parser);
+        let req_body = self.request(prompt.system.as_deref(), &[], content, &self.summarize_options, false);
+
+        let (info, _) = retry::retry(&self.retry, self.retry.parse_retries, Error::is_parsing, || {
+            self.complete_parsed(&req_body, &format, |choice| {
+                parser.parse_summarize_response(&choice.message.content)
+                    .and_then(|infos| parsing::SummarizedArguments(infos).merged())
+            })
+        }).await?;
+
+        Ok(info)
+    }
+
+    async fn predict(&self, prompt: &settings::Prompt, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], cfg: &settings::PredictionCfg, history: &[chat::Message]) -> Result<chat::Prediction, llm::Error> {
+        let confidence = &cfg.confidence;
+        let parser = parsing::Parser::new(&prompt.format);
+        let content = prompt.render_predict(arg_a, arg_b, &parsing::render_demonstrations(&parsing::predict_demonstrations(examples, &prompt.arguments, false, &parser)));
+        let format = parsing::predict_format(false, &parser, confidence, &cfg.taxonomy);
+        let req_body = self.request(
+            prompt.system.as_deref(),
+            history,
+            content,
+            &self.predict_options,
+            matches!(confidence, settings::ConfidenceCfg::LogProbs),
+        );
+
+        let ((prediction, response), messages) = retry::retry(&self.retry, self.retry.parse_retries, Error::is_parsing, || {
+            self.complete_parsed(
+                &req_body,
+                &format,
+                |choice| parsing::parse_prediction(
+                    &choice.message.content,
+                    false,
+                    &parser,
+                    cfg,
+                    choice.logprobs.as_ref().and_then(|logprobs| logprobs.content.as_deref()),
+                ).map(|prediction| (prediction, choice.message.content.clone())),
+            )
+        }).await?;
+
+        Ok(chat::Prediction {
+            relation: models::Relation {
+                arg_a_id: arg_a.id.unwrap(),
+                arg_b_id: arg_b.id.unwrap(),
+                relation_type: cfg.taxonomy.map(prediction.relation_type),
+                confidence: prediction.confidence,
+                explanation: prediction.explanation,
+                votes: Vec::new(),
+                contradicted: false,
+            },
+            turns: chat::turns(&messages, &response),
+        })
+    }
+
+    async fn embed(&self, content: &str) -> Result<Vec<f32>, llm::Error> {
+        let req_body = EmbeddingRequestBody {
+            model: self.embedding_model.clone(),
+            input: String::from(content),
+        };
+
+        let start = std::time::Instant::now();
+        let body: EmbeddingSuccessResponseBody = self.post("/v1/embeddings", &req_body).await?;
+
+        self.metrics.record(metrics::Usage {
+            prompt_tokens: body.usage.as_ref().map_or(0, |usage| usage.prompt_tokens),
+            completion_tokens: 0,
+            latency: start.elapsed(),
+            generation: None,
+        });
+
+        Ok(body.data.into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or(Error::ApiError(String::from("no embedding found in response body")))?)
+    }
+
+    fn inference_stats(&self) -> metrics::InferenceStats {
+        self.metrics.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::llm::ClientTrait;
+
+    fn client(uri: String, api_key: Option<&str>) -> Client {
+        Client::new(&settings::OpenAICompatibleCfg {
+            uri,
+            model: String::from("test-model"),
+            embedding_model: Some(String::from("test-embedding-model")),
+            api_key: api_key.map(String::from),
+            retry: settings::RetryCfg {
+                max_retries: 0,
+                parse_retries: 0,
+                ..Default::default()
+            },
+            context: settings::ContextCfg::default(),
+            options: settings::ModelOptions::default(),
+            summarize_options: settings::ModelOptions::default(),
+            predict_options: settings::ModelOptions {
+                temperature: Some(0.7),
+                seed: Some(42),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn post(content: &str) -> models::Post {
+        models::Post {
+            content: String::from(content),
+            author: None,
+            thread_context: None,
+        }
+    }
+
+    fn prompt() -> settings::Prompt {
+        settings::Prompt {
+            system: Some(String::from("You are an argument mining assistant.")),
+            prompt: String::from("Summarize the argument."),
+            format: settings::ResponseFormatCfg::KeyValue,
+            arguments: settings::ArgumentFieldsCfg::Raw,
+            endpoint: settings::EndpointCfg::Generate,
+        }
+    }
+
+    fn completion(content: &str) -> String {
+        serde_json::json!({
+            "id": "chatcmpl-0",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop",
+            }],
+        }).to_string()
+    }
+
+    mod summarize {
+        use super::*;
+
+        #[tokio::test]
+        async fn parses_completion() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/v1/chat/completions")
+                .match_header("authorization", "Bearer secret")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "model": "test-model",
+                    "messages": [
+                        { "role": "system", "content": "You are an argument mining assistant." },
+                        { "role": "user", "content": "Summarize the argument.\n\nArg:Taxes are too high.\n" },
+                    ],
+                })))
+                .with_header("content-type", "application/json")
+                .with_body(completion("Title: Taxes\nSummary: Taxes should be lowered."))
+                .create_async()
+                .await;
+
+            let info = client(server.url(), Some("secret"))
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(info[0].title, "Taxes");
+            assert_eq!(info[0].summary, "Taxes should be lowered.");
+        }
+
+        #[tokio::test]
+        async fn surfaces_api_error() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/v1/chat/completions")
+                .with_status(404)
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"error": {"message": "model not found"}}"#)
+                .create_async()
+                .await;
+
+            let res = client(server.url(), None)
+                .summarize(&prompt(), None, &post("Taxes are too high."), &[])
+                .await;
+
+            assert!(matches!(
+                res,
+                Err(llm::Error::OpenAICompatible(Error::ApiError(ref msg))) if msg == "model not found",
+            ));
+        }
+    }
+
+    mod predict {
+        use super::*;
+
+        #[tokio::test]
+        async fn parses_completion() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/v1/chat/completions")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "temperature": 0.7, "seed": 42 })))
+                .with_header("content-type", "application/json")
+                .with_body(completion("Relation: Support\nExplanation: Both posts argue for lower taxes."))
+                .create_async()
+                .await;
+
+            let info = SummarizedInfo { title: String::new(), summary: String::new(), quote: None, structure: None };
+            let arg_a = models::Argument::with_id(1, info.clone(), String::from("A"), None);
+            let arg_b = models::Argument::with_id(2, info, String::from("B"), None);
+
+            let relation = client(server.url(), None)
+                .predict(&prompt(), &arg_a, &arg_b, &[], &settings::PredictionCfg::default(), &[])
+                .await
+                .unwrap()
+                .relation;
+
+            mock.assert_async().await;
+            assert_eq!(relation.arg_a_id, 1);
+            assert_eq!(relation.arg_b_id, 2);
+            assert!(matches!(relation.relation_type, models::RelationType::Support));
+            assert_eq!(relation.confidence, 1.0);
+            assert_eq!(relation.explanation, "Both posts argue for lower taxes.");
+        }
+
+        #[tokio::test]
+        async fn reads_label_logprobs() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/v1/chat/completions")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "logprobs": true })))
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::json!({
+                    "choices": [{
+                        "message": { "role": "assistant", "content": "Relation: Attack" },
+                        "logprobs": { "content": [
+                            { "token": "Relation", "logprob": 0.0 },
+                            { "token": ": ", "logprob": 0.0 },
+                            { "token": "Attack", "logprob": 0.8_f64.ln() },
+                        ] },
+                    }],
+                }).to_string())
+                .create_async()
+                .await;
+
+            let info = SummarizedInfo { title: String::new(), summary: String::new(), quote: None, structure: None };
+            let arg_a = models::Argument::with_id(1, info.clone(), String::from("A"), None);
+            let arg_b = models::Argument::with_id(2, info, String::from("B"), None);
+
+            let relation = client(server.url(), None)
+                .predict(&prompt(), &arg_a, &arg_b, &[], &settings::PredictionCfg {
+                    confidence: settings::ConfidenceCfg::LogProbs,
+                    ..Default::default()
+                }, &[])
+                .await
+                .unwrap()
+                .relation;
+
+            mock.assert_async().await;
+            assert!((relation.confidence - 0.8).abs() < 1e-6);
+        }
+    }
+
+    mod complete_parsed {
+        use super::*;
+
+        #[tokio::test]
+        async fn continues_conversation_for_repair() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/v1/chat/completions")
+                .with_header("content-type", "application/json")
+                .with_body(completion("I am not sure."))
+                .create_async()
+                .await;
+            let repair = server.mock("POST", "/v1/chat/completions")
+                .match_body(mockito::Matcher::Regex(String::from(
+                    r#"\{"role":"assistant","content":"I am not sure."\},\{"role":"user","content":"It could not be read"#,
+                )))
+                .with_header("content-type", "application/json")
+                .with_body(completion("Relation: Support"))
+                .create_async()
+                .await;
+
+            let mut client = client(server.url(), None);
+            client.retry.repair_attempts = 1;
+
+            let info = SummarizedInfo { title: String::new(), summary: String::new(), quote: None, structure: None };
+            let arg_a = models::Argument::with_id(1, info.clone(), String::from("A"), None);
+            let arg_b = models::Argument::with_id(2, info, String::from("B"), None);
+
+            let prediction = client
+                .predict(&prompt(), &arg_a, &arg_b, &[], &settings::PredictionCfg::default(), &[])
+                .await
+                .unwrap();
+
+            repair.assert_async().await;
+            assert!(matches!(prediction.relation.relation_type, models::RelationType::Support));
+            assert_eq!(prediction.turns.len(), 4);
+            assert_eq!(prediction.turns[1], chat::Message::assistant(String::from("I am not sure.")));
+            assert!(matches!(prediction.turns[2].role, chat::Role::User));
+            assert_eq!(prediction.turns[3], chat::Message::assistant(String::from("Relation: Support")));
+        }
+    }
+
+    mod embed {
+        use super::*;
+
+        #[tokio::test]
+        async fn uses_embedding_model() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/v1/embeddings")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "model": "test-embedding-model",
+                    "input": "Taxes should be lower.",
+                })))
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"object": "list", "data": [{"object": "embedding", "index": 0, "embedding": [0.25, 0.75]}]}"#)
+                .create_async()
+                .await;
+
+            let embedding = client(server.url(), None)
+                .embed("Taxes should be lower.")
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(embedding, vec![0.25, 0.75]);
+        }
+    }
+}
@@ -0,0 +1,652 @@
+mod json;
+mod key_value;
+mod xml_tags;
+mod yaml;
+
+use std::ops::Range;
+use regex::Regex;
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+
+use crate::clients::llm::confidence::{self, TokenLogProb};
+use crate::configuration::settings::{ArgumentFieldsCfg, ConfidenceCfg, PredictionCfg, ResponseFormatCfg, TaxonomyCfg};
+use crate::models::{self, SummarizedInfo};
+
+/// The different kinds of error expected while parsing a Large Language Model
+/// response, whatever the backend it comes from.
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("no element found in LLM response")]
+    NoElementFound,
+
+    #[error("no \"{0}\" element found in LLM response")]
+    MissingElement(&'static str),
+
+    #[error("invalid structured output: {0}")]
+    InvalidStructuredOutput(#[from] serde_json::Error),
+
+    #[error("unknown relation \"{0}\" in LLM response")]
+    UnknownRelation(String),
+
+    #[error("invalid YAML output: {0}")]
+    InvalidYaml(#[from] serde_yaml::Error),
+
+    #[error("no log-probabilities found for the relation label in LLM response")]
+    MissingLogProbs,
+}
+
+/// The structured content expected from a summarization response, listing
+/// every argument of the post.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SummarizeResponse {
+    pub arguments: Vec<SummarizedInfo>,
+}
+
+/// The arguments parsed from a summarization response, whether it lists them
+/// in an `arguments` field, as a bare list, or gives a single argument.
+#[derive(Deserialize)]
+#[serde(from = "AnySummarizeResponse")]
+pub struct SummarizedArguments(pub Vec<SummarizedInfo>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnySummarizeResponse {
+    Listed(SummarizeResponse),
+    List(Vec<SummarizedInfo>),
+    Single(SummarizedInfo),
+}
+
+impl From<AnySummarizeResponse> for SummarizedArguments {
+    fn from(response: AnySummarizeResponse) -> Self {
+        match response {
+            AnySummarizeResponse::Listed(listed) => SummarizedArguments(listed.arguments),
+            AnySummarizeResponse::List(list) => SummarizedArguments(list),
+            AnySummarizeResponse::Single(info) => SummarizedArguments(vec![info]),
+        }
+    }
+}
+
+impl SummarizedArguments {
+    /// Return the argument merging the partial summaries of a post, the first
+    /// one if the model gave several.
+    pub fn merged(self) -> Result<SummarizedInfo, Error> {
+        if self.0.len() > 1 {
+            log::debug!("{} arguments given when merging partial summaries, keeping the first one", self.0.len());
+        }
+
+        self.0.into_iter()
+            .next()
+            .ok_or(Error::NoElementFound)
+    }
+}
+
+/// The structured content expected from a relation prediction response, its
+/// relation label being read by [`parse_relation`].
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PredictResponse {
+    pub relation: String,
+    /// A short rationale of the model for the relation.
+    pub explanation: String,
+}
+
+/// The structured content expected from a relation prediction response when
+/// the model is asked for its confidence.
+#[derive(Deserialize, JsonSchema)]
+pub struct VerbalizedPredictResponse {
+    #[serde(flatten)]
+    pub prediction: PredictResponse,
+    /// The confidence of the model in the relation, between 0.0 and 1.0.
+    pub confidence: f32,
+}
+
+/// A relation parsed from a prediction response, along with its estimated
+/// confidence and the rationale of the model.
+pub struct Prediction {
+    pub relation_type: models::RelationType,
+    pub confidence: f32,
+    pub explanation: String,
+}
+
+/// A parser of the responses written in the output format of a prompt.
+pub enum Parser {
+    KeyValue(key_value::KeyValueParser),
+    Json(json::JsonParser),
+    Yaml(yaml::YamlParser),
+    XmlTags(xml_tags::XmlTagsParser),
+}
+
+impl Parser {
+    pub fn new(format: &ResponseFormatCfg) -> Self {
+        match format {
+            ResponseFormatCfg::KeyValue => Parser::KeyValue(key_value::KeyValueParser),
+            ResponseFormatCfg::Json => Parser::Json(json::JsonParser),
+            ResponseFormatCfg::Yaml => Parser::Yaml(yaml::YamlParser),
+            ResponseFormatCfg::XmlTags => Parser::XmlTags(xml_tags::XmlTagsParser),
+        }
+    }
+}
+
+/// Trait defining the parsing of the free text responses of a Large Language
+/// Model written in a given output format.
+pub trait ResponseParser {
+    /// Parse the arguments of a summarization response, in order of
+    /// appearance.
+    fn parse_summarize_response(&self, response: &str) -> Result<Vec<SummarizedInfo>, Error>;
+
+    /// Parse a relation prediction response.
+    fn parse_predict_response(&self, response: &str) -> Result<PredictResponse, Error>;
+
+    /// Parse a relation prediction response along with the confidence of the
+    /// model.
+    fn parse_verbalized_predict_response(&self, response: &str) -> Result<VerbalizedPredictResponse, Error>;
+
+    /// Locate the relation label in a prediction response.
+    fn relation_span(&self, response: &str) -> Option<Range<usize>>;
+
+    /// Describe the format expected from a summarization response, to remind
+    /// it to the model when its response could not be parsed.
+    fn summarize_format(&self) -> String;
+
+    /// Describe the format expected from a relation prediction response with
+    /// the given confidence estimation method.
+    fn predict_format(&self, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String;
+
+    /// Write a summarization response listing the given arguments in the
+    /// format, as the answer of a few-shot demonstration.
+    fn format_summarize_response(&self, infos: &[SummarizedInfo]) -> String;
+
+    /// Write a relation prediction response in the format, as the answer of a
+    /// few-shot demonstration.
+    fn format_predict_response(&self, response: &PredictResponse) -> String;
+}
+
+impl ResponseParser for Parser {
+    fn parse_summarize_response(&self, response: &str) -> Result<Vec<SummarizedInfo>, Error> {
+        match self {
+            Parser::KeyValue(parser) => parser.parse_summarize_response(response),
+            Parser::Json(parser) => parser.parse_summarize_response(response),
+            Parser::Yaml(parser) => parser.parse_summarize_response(response),
+            Parser::XmlTags(parser) => parser.parse_summarize_response(response),
+        }
+    }
+
+    fn parse_predict_response(&self, response: &str) -> Result<PredictResponse, Error> {
+        match self {
+            Parser::KeyValue(parser) => parser.parse_predict_response(response),
+            Parser::Json(parser) => parser.parse_predict_response(response),
+            Parser::Yaml(parser) => parser.parse_predict_response(response),
+            Parser::XmlTags(parser) => parser.parse_predict_response(response),
+        }
+    }
+
+    fn parse_verbalized_predict_response(&self, response: &str) -> Result<VerbalizedPredictResponse, Error> {
+        match self {
+            Parser::KeyValue(parser) => parser.parse_verbalized_predict_response(response),
+            Parser::Json(parser) => parser.parse_verbalized_predict_response(response),
+            Parser::Yaml(parser) => parser.parse_verbalized_predict_response(response),
+            Parser::XmlTags(parser) => parser.parse_verbalized_predict_response(response),
+        }
+    }
+
+    fn relation_span(&self, response: &str) -> Option<Range<usize>> {
+        match self {
+            Parser::KeyValue(parser) => parser.relation_span(response),
+            Parser::Json(parser) => parser.relation_span(response),
+            Parser::Yaml(parser) => parser.relation_span(response),
+            Parser::XmlTags(parser) => parser.relation_span(response),
+        }
+    }
+
+    fn summarize_format(&self) -> String {
+        match self {
+            Parser::KeyValue(parser) => parser.summarize_format(),
+            Parser::Json(parser) => parser.summarize_format(),
+            Parser::Yaml(parser) => parser.summarize_format(),
+            Parser::XmlTags(parser) => parser.summarize_format(),
+        }
+    }
+
+    fn predict_format(&self, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String {
+        match self {
+            Parser::KeyValue(parser) => parser.predict_format(confidence, taxonomy),
+            Parser::Json(parser) => parser.predict_format(confidence, taxonomy),
+            Parser::Yaml(parser) => parser.predict_format(confidence, taxonomy),
+            Parser::XmlTags(parser) => parser.predict_format(confidence, taxonomy),
+        }
+    }
+
+    fn format_summarize_response(&self, infos: &[SummarizedInfo]) -> String {
+        match self {
+            Parser::KeyValue(parser) => parser.format_summarize_response(infos),
+            Parser::Json(parser) => parser.format_summarize_response(infos),
+            Parser::Yaml(parser) => parser.format_summarize_response(infos),
+            Parser::XmlTags(parser) => parser.format_summarize_response(infos),
+        }
+    }
+
+    fn format_predict_response(&self, response: &PredictResponse) -> String {
+        match self {
+            Parser::KeyValue(parser) => parser.format_predict_response(response),
+            Parser::Json(parser) => parser.format_predict_response(response),
+            Parser::Yaml(parser) => parser.format_predict_response(response),
+            Parser::XmlTags(parser) => parser.format_predict_response(response),
+        }
+    }
+}
+
+/// Generate the JSON schema of the structured output `T`, with every
+/// sub-schema inlined as some backends do not resolve `$ref`.
+pub fn schema_for<T: JsonSchema>() -> serde_json::Value {
+    let schema = SchemaSettings::draft07()
+        .with(|s| {
+            s.inline_subschemas = true;
+            s.meta_schema = None;
+        })
+        .into_generator()
+        .into_root_schema_for::<T>();
+
+    serde_json::to_value(schema).unwrap()
+}
+
+/// Parse a response constrained by the JSON schema of `T`.
+pub fn parse_structured_response<T: DeserializeOwned>(response: &str) -> Result<T, Error> {
+    Ok(serde_json::from_str(response.trim())?)
+}
+
+/// Parse a response into `T`, first as a structured output if `structured` is
+/// set, and falling back to the given free text parser otherwise.
+pub fn parse_with_fallback<T: DeserializeOwned>(
+    response: &str,
+    structured: bool,
+    fallback: impl Fn(&str) -> Result<T, Error>,
+) -> Result<T, Error> {
+    if structured {
+        match parse_structured_response(response) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => log::debug!("failed to parse structured output, falling back to free text parsing: {}", e),
+        }
+    }
+
+    fallback(response)
+}
+
+/// Return the JSON schema of the structured output expected from a relation
+/// prediction with the given confidence estimation method, its relation
+/// restricted to the labels of the taxonomy.
+pub fn predict_schema(confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> serde_json::Value {
+    let mut schema = match confidence {
+        ConfidenceCfg::Verbalized => schema_for::<VerbalizedPredictResponse>(),
+        _ => schema_for::<PredictResponse>(),
+    };
+
+    schema["properties"]["relation"] = serde_json::json!({
+        "type": "string",
+        "enum": taxonomy.labels().iter()
+            .map(|relation| relation.to_str())
+            .collect::<Vec<&str>>(),
+    });
+
+    schema
+}
+
+/// List the labels of a taxonomy for a response format description (e.g.,
+/// "Support, Attack or NotRelated").
+pub fn describe_labels(taxonomy: &TaxonomyCfg) -> String {
+    let labels: Vec<&str> = taxonomy.labels().iter()
+        .map(|relation| relation.to_str())
+        .collect();
+
+    match labels.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, others)) => format!("{} or {}", others.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// Parse a relation prediction response with the free text `parser` and
+/// estimate its confidence with the given method. The `logprobs` of the
+/// response tokens are only required by the `LogProbs` method.
+pub fn parse_prediction(
+    response: &str,
+    structured: bool,
+    parser: &Parser,
+    cfg: &PredictionCfg,
+    logprobs: Option<&[TokenLogProb]>,
+) -> Result<Prediction, Error> {
+    if let ConfidenceCfg::Verbalized = cfg.confidence {
+        let parsed = parse_with_fallback(response, structured, |response| parser.parse_verbalized_predict_response(response))?;
+
+        return Ok(Prediction {
+            relation_type: parse_relation(&parsed.prediction.relation, cfg)?,
+            confidence: parsed.confidence.clamp(0.0, 1.0),
+            explanation: parsed.prediction.explanation,
+        })
+    }
+
+    let parsed = parse_with_fallback(response, structured, |response| parser.parse_predict_response(response))?;
+    let relation_type = parse_relation(&parsed.relation, cfg)?;
+
+    let confidence = match cfg.confidence {
+        ConfidenceCfg::LogProbs => parser.relation_span(response)
+            .or_else(|| relation_span(response))
+            .zip(logprobs)
+            .and_then(|(span, logprobs)| confidence::span_probability(logprobs, span))
+            .ok_or(Error::MissingLogProbs)?,
+        _ => 1.0_f32,
+    };
+
+    Ok(Prediction {
+        relation_type,
+        confidence,
+        explanation: parsed.explanation,
+    })
+}
+
+/// Read the relation label of a response, first among the configured label
+/// aliases, keyed by their normalized label, then among the labels of the
+/// relations, failing on an unknown label rather than storing a made-up
+/// relation.
+pub fn parse_relation(label: &str, cfg: &PredictionCfg) -> Result<models::RelationType, Error> {
+    cfg.label_aliases.get(&models::normalize_label(label))
+        .cloned()
+        .or_else(|| models::RelationType::parse(label))
+        .ok_or_else(|| Error::UnknownRelation(String::from(label.trim())))
+}
+
+/// Describe the format expected from a summarization response, to remind it
+/// to the model when its response could not be parsed.
+pub fn summarize_format(structured: bool, parser: &Parser) -> String {
+    if structured {
+        format!("a JSON object following the schema {}", schema_for::<SummarizeResponse>())
+    } else {
+        parser.summarize_format()
+    }
+}
+
+/// Describe the format expected from a relation prediction response with the
+/// given confidence estimation method, to remind it to the model when its
+/// response could not be parsed.
+pub fn predict_format(structured: bool, parser: &Parser, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String {
+    if structured {
+        format!("a JSON object following the schema {}", predict_schema(confidence, taxonomy))
+    } else {
+        parser.predict_format(confidence, taxonomy)
+    }
+}
+
+/// A few-shot demonstration: the input of an example along with the answer
+/// expected from the model.
+pub struct Demonstration {
+    pub input: String,
+    pub answer: String,
+}
+
+/// Build few-shot summarization demonstrations, their answers written as
+/// structured outputs if `structured` is set, or in the format of `parser`
+/// otherwise.
+pub fn summarize_demonstrations(examples: &[models::SummarizeExample], structured: bool, parser: &Parser) -> Vec<Demonstration> {
+    let json = Parser::new(&ResponseFormatCfg::Json);
+    let parser = if structured { &json } else { parser };
+
+    examples.iter()
+        .map(|example| Demonstration {
+            input: format!("Arg:{}", example.content),
+            answer: parser.format_summarize_response(&[SummarizedInfo {
+                title: example.title.clone(),
+                summary: example.summary.clone(),
+                quote: example.quote.clone(),
+                structure: example.structure.clone(),
+            }]),
+        })
+        .collect()
+}
+
+/// Build few-shot relation prediction demonstrations, their arguments shown
+/// with the representation of `fields` and their answers written as
+/// structured outputs if `structured` is set, or in the format of `parser`
+/// otherwise.
+pub fn predict_demonstrations(examples: &[models::PredictExample], fields: &ArgumentFieldsCfg, structured: bool, parser: &Parser) -> Vec<Demonstration> {
+    let json = Parser::new(&ResponseFormatCfg::Json);
+    let parser = if structured { &json } else { parser };
+
+    examples.iter()
+        .map(|example| {
+            let (arg_a, arg_b) = example.arguments();
+
+            Demonstration {
+                input: format!("Arg1:{}\nArg2:{}", fields.represent(&arg_a), fields.represent(&arg_b)),
+                answer: parser.format_predict_response(&PredictResponse {
+                    relation: String::from(example.relation.to_str()),
+                    explanation: example.explanation.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Render few-shot demonstrations as the text injected in a prompt, each
+/// input followed by its answer.
+pub fn render_demonstrations(demonstrations: &[Demonstration]) -> String {
+    demonstrations.iter()
+        .map(|demonstration| format!("{}\n{}", demonstration.input, demonstration.answer))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Locate the relation label in a prediction response, whether it is written
+/// as a `Relation: ...` line, a `relation: ...` YAML field or as a
+/// `"relation": "..."` JSON field.
+pub fn relation_span(response: &str) -> Option<Range<usize>> {
+    let re = Regex::new(r#"(?i)relation"?\s*:\s*"?(?<relation>[^"\n,}]+)"#).unwrap();
+
+    re.captures(response)
+        .and_then(|caps| caps.name("relation"))
+        .map(|relation| relation.range())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod schema_for {
+        use super::*;
+
+        #[test]
+        fn inlines_relation_type() {
+            let schema = schema_for::<PredictResponse>();
+
+            assert!(schema["properties"]["relation"].get("$ref").is_none());
+            assert!(schema.get("definitions").is_none());
+        }
+    }
+
+    mod predict_schema {
+        use super::*;
+
+        #[test]
+        fn restricts_relation_to_taxonomy() {
+            let schema = predict_schema(&ConfidenceCfg::Constant, &TaxonomyCfg::Coarse);
+
+            assert_eq!(
+                schema["properties"]["relation"]["enum"],
+                serde_json::json!(["Support", "Attack", "NotRelated"]),
+            );
+        }
+
+        #[test]
+        fn requires_verbalized_confidence() {
+            let schema = predict_schema(&ConfidenceCfg::Verbalized, &TaxonomyCfg::Fine);
+
+            assert_eq!(schema["properties"]["confidence"]["type"], "number");
+            assert!(schema["required"].as_array().unwrap().contains(&serde_json::json!("confidence")));
+            assert!(schema["required"].as_array().unwrap().contains(&serde_json::json!("relation")));
+        }
+    }
+
+    mod parse_structured_response {
+        use super::*;
+
+        #[test]
+        fn parses_single_or_listed_arguments() {
+            let single: SummarizedArguments = parse_structured_response(
+                r#"{"title": "Taxes", "summary": "Taxes should be lower."}"#,
+            ).unwrap();
+            let listed: SummarizedArguments = parse_structured_response(
+                r#"{"arguments": [{"title": "Taxes", "summary": "Taxes should be lower."}, {"title": "Spending", "summary": "Spending should be cut."}]}"#,
+            ).unwrap();
+
+            assert_eq!(single.0.len(), 1);
+            assert_eq!(listed.0.len(), 2);
+            assert_eq!(listed.0[1].title, "Spending");
+        }
+
+        #[test]
+        fn parses_summarized_info() {
+            let info: SummarizedInfo = parse_structured_response(
+                r#"{"title": "Taxes", "summary": "Taxes should be lower."}"#,
+            ).unwrap();
+
+            assert_eq!(info.title, "Taxes");
+            assert_eq!(info.summary, "Taxes should be lower.");
+        }
+
+    }
+
+    mod parse_prediction {
+        use super::*;
+
+        fn cfg(confidence: ConfidenceCfg) -> PredictionCfg {
+            PredictionCfg {
+                confidence,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn rejects_unknown_relation() {
+            assert!(matches!(
+                parse_prediction(r#"{"relation": "Maybe", "explanation": ""}"#, true, &Parser::new(&ResponseFormatCfg::Json), &cfg(ConfidenceCfg::Constant), None),
+                Err(Error::UnknownRelation(label)) if label == "Maybe",
+            ));
+        }
+
+        #[test]
+        fn reads_label_aliases() {
+            let cfg = PredictionCfg {
+                label_aliases: [(String::from("neutral"), models::RelationType::NotRelated)].into(),
+                ..Default::default()
+            };
+
+            let prediction = parse_prediction("Relation: **neutral**", false, &Parser::new(&ResponseFormatCfg::KeyValue), &cfg, None)
+                .unwrap();
+
+            assert_eq!(prediction.relation_type, models::RelationType::NotRelated);
+        }
+
+        #[test]
+        fn reads_verbalized_confidence() {
+            let prediction = parse_prediction(
+                r#"{"relation": "Attack", "explanation": "The posts disagree.", "confidence": 0.7}"#,
+                true,
+                &Parser::new(&ResponseFormatCfg::KeyValue),
+                &cfg(ConfidenceCfg::Verbalized),
+                None,
+            ).unwrap();
+
+            assert!(matches!(prediction.relation_type, models::RelationType::Attack));
+            assert_eq!(prediction.confidence, 0.7);
+            assert_eq!(prediction.explanation, "The posts disagree.");
+        }
+
+        #[test]
+        fn reads_verbalized_confidence_from_free_text() {
+            let prediction = parse_prediction(
+                "Relation: Support\nConfidence: 0.25",
+                true,
+                &Parser::new(&ResponseFormatCfg::KeyValue),
+                &cfg(ConfidenceCfg::Verbalized),
+                None,
+            ).unwrap();
+
+            assert!(matches!(prediction.relation_type, models::RelationType::Support));
+            assert_eq!(prediction.confidence, 0.25);
+        }
+
+        #[test]
+        fn reads_label_logprobs() {
+            let response = r#"{"relation": "Attack", "explanation": ""}"#;
+            let logprobs = [
+                TokenLogProb { token: String::from(r#"{"relation": ""#), logprob: 0.0 },
+                TokenLogProb { token: String::from("Attack"), logprob: 0.5_f64.ln() },
+                TokenLogProb { token: String::from(r#"", "explanation": ""}"#), logprob: 0.0 },
+            ];
+
+            let prediction = parse_prediction(response, true, &Parser::new(&ResponseFormatCfg::Json), &cfg(ConfidenceCfg::LogProbs), Some(&logprobs))
+                .unwrap();
+
+            assert!((prediction.confidence - 0.5).abs() < 1e-6);
+        }
+
+        #[test]
+        fn fails_without_logprobs() {
+            assert!(matches!(
+                parse_prediction("Relation: Attack", false, &Parser::new(&ResponseFormatCfg::KeyValue), &cfg(ConfidenceCfg::LogProbs), None),
+                Err(Error::MissingLogProbs),
+            ));
+        }
+    }
+
+    mod predict_demonstrations {
+        use super::*;
+
+        #[test]
+        fn writes_answers_in_prompt_format() {
+            let demonstrations = render_demonstrations(&predict_demonstrations(
+                &[models::PredictExample {
+                    arg_a: String::from("Taxes are too high."),
+                    arg_b: String::from("We should cut taxes."),
+                    summary_a: None,
+                    summary_b: None,
+                    relation: models::RelationType::Support,
+                    explanation: String::from("Both posts want lower taxes."),
+                }],
+                &ArgumentFieldsCfg::Raw,
+                false,
+                &Parser::new(&ResponseFormatCfg::KeyValue),
+            ));
+
+            assert_eq!(
+                demonstrations,
+                "Arg1:Taxes are too high.\nArg2:We should cut taxes.\nRelation: Support\nExplanation: Both posts want lower taxes.",
+            );
+        }
+
+        #[test]
+        fn represents_arguments_with_selected_fields() {
+            let summary = |title: &str, summary: &str| Some(SummarizedInfo {
+                title: String::from(title),
+                summary: String::from(summary),
+                quote: None,
+                structure: None,
+            });
+
+            let demonstrations = predict_demonstrations(
+                &[models::PredictExample {
+                    arg_a: String::from("Taxes are too high!"),
+                    arg_b: String::from("Cut taxes now!"),
+                    summary_a: summary("Taxes", "Taxes should be lower."),
+                    summary_b: summary("Tax cut", "Taxes should be cut."),
+                    relation: models::RelationType::Support,
+                    explanation: String::new(),
+                }],
+                &ArgumentFieldsCfg::TitleSummary,
+                false,
+                &Parser::new(&ResponseFormatCfg::KeyValue),
+            );
+
+            assert_eq!(demonstrations[0].input, "Arg1:Taxes\nTaxes should be lower.\nArg2:Tax cut\nTaxes should be cut.");
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::ops::Range;
+use serde::de::DeserializeOwned;
+
+use crate::clients::llm::parsing::{self, Error, PredictResponse, ResponseParser, SummarizedArguments, SummarizeResponse, VerbalizedPredictResponse};
+use crate::configuration::settings::{ConfidenceCfg, TaxonomyCfg};
+use crate::models::SummarizedInfo;
+
+/// Parse responses written as a JSON object or list, possibly surrounded by
+/// other text such as a Markdown code fence.
+pub struct JsonParser;
+
+impl JsonParser {
+    /// Deserialize the outermost JSON object or list of a response.
+    fn parse<T: DeserializeOwned>(&self, response: &str) -> Result<T, Error> {
+        let Some(start) = response.find(['{', '[']) else {
+            return Err(Error::NoElementFound)
+        };
+
+        let closing = if response[start..].starts_with('{') { '}' } else { ']' };
+
+        let Some(end) = response.rfind(closing).filter(|&end| end > start) else {
+            return Err(Error::NoElementFound)
+        };
+
+        parsing::parse_structured_response(&response[start..=end])
+    }
+}
+
+impl ResponseParser for JsonParser {
+    fn parse_summarize_response(&self, response: &str) -> Result<Vec<SummarizedInfo>, Error> {
+        self.parse::<SummarizedArguments>(response)
+            .map(|arguments| arguments.0)
+    }
+
+    fn parse_predict_response(&self, response: &str) -> Result<PredictResponse, Error> {
+        self.parse(response)
+    }
+
+    fn parse_verbalized_predict_response(&self, response: &str) -> Result<VerbalizedPredictResponse, Error> {
+        self.parse(response)
+    }
+
+    fn relation_span(&self, response: &str) -> Option<Range<usize>> {
+        parsing::relation_span(response)
+    }
+
+    fn summarize_format(&self) -> String {
+        String::from(r#"{"arguments": [{"title": "<title>", "summary": "<summary>", "quote": "<passage of the post stating the argument, verbatim>", "structure": {"claim": "<main claim>", "premises": ["<premise>"], "conclusion": "<conclusion or warrant, or null>"}}]}"#)
+    }
+
+    fn predict_format(&self, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String {
+        let labels = parsing::describe_labels(taxonomy);
+
+        match confidence {
+            ConfidenceCfg::Verbalized => format!(
+                r#"{{"relation": "<{labels}>", "explanation": "<explanation>", "confidence": <a number between 0.0 and 1.0>}}"#,
+            ),
+            _ => format!(r#"{{"relation": "<{labels}>", "explanation": "<explanation>"}}"#),
+        }
+    }
+
+    fn format_summarize_response(&self, infos: &[SummarizedInfo]) -> String {
+        serde_json::to_string(&SummarizeResponse { arguments: infos.to_vec() }).unwrap()
+    }
+
+    fn format_predict_response(&self, response: &PredictResponse) -> String {
+        serde_json::to_string(response).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_summarize_response {
+        use super::*;
+
+        #[test]
+        fn parses_bare_list() {
+            let infos = JsonParser.parse_summarize_response(
+                r#"Sure: [{"title": "Taxes", "summary": "Taxes should be lower."}]"#,
+            ).unwrap();
+
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].title, "Taxes");
+        }
+    }
+
+    mod parse_predict_response {
+        use super::*;
+
+        #[test]
+        fn parses_fenced_object() {
+            let predicted = JsonParser.parse_predict_response(
+                "```json\n{\"relation\": \"Attack\", \"explanation\": \"The posts disagree.\"}\n```",
+            ).unwrap();
+
+            assert_eq!(predicted.relation, "Attack");
+            assert_eq!(predicted.explanation, "The posts disagree.");
+        }
+
+        #[test]
+        fn fails_without_object() {
+            assert!(matches!(
+                JsonParser.parse_predict_response("Relation: Attack"),
+                Err(Error::NoElementFound),
+            ));
+        }
+    }
+}
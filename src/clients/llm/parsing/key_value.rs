@@ -0,0 +1,240 @@
+use std::ops::Range;
+use regex::Regex;
+
+use crate::clients::llm::parsing::{self, Error, PredictResponse, ResponseParser, VerbalizedPredictResponse};
+use crate::configuration::settings::{ConfidenceCfg, TaxonomyCfg};
+use crate::models::{ArgumentStructure, SummarizedInfo};
+
+/// Parse responses written as `Key: value` lines.
+pub struct KeyValueParser;
+
+impl KeyValueParser {
+    /// Parse the optional `Claim: ...`, `Premise: ...` and `Conclusion: ...`
+    /// lines of an argument, its structure being left out without a claim.
+    fn parse_structure(&self, argument: &str) -> Option<ArgumentStructure> {
+        let line = |key: &str| Regex::new(&format!(r"(?m)^{key}: (?<value>.*)$")).unwrap();
+
+        let claim = line("Claim").captures(argument)?;
+
+        Some(ArgumentStructure {
+            claim: String::from(claim["value"].trim()),
+            premises: line("Premise").captures_iter(argument)
+                .map(|caps| String::from(caps["value"].trim()))
+                .collect(),
+            conclusion: line("Conclusion").captures(argument)
+                .map(|caps| String::from(caps["value"].trim())),
+        })
+    }
+}
+
+impl ResponseParser for KeyValueParser {
+    /// Parse a summarization response expected to follow the
+    /// `Title: ...\nSummary: ...` format, repeated for each argument and
+    /// optionally followed by the quoted passage and the structure of the
+    /// argument.
+    fn parse_summarize_response(&self, response: &str) -> Result<Vec<SummarizedInfo>, Error> {
+        let re = Regex::new(r"Title: (?<title>.*)\nSummary: (?<summary>.*)").unwrap();
+
+        let captures: Vec<regex::Captures> = re.captures_iter(response).collect();
+
+        // The lines of an argument run until the title of the next one.
+        let ends = captures.iter()
+            .skip(1)
+            .map(|caps| caps.get(0).unwrap().start())
+            .chain(std::iter::once(response.len()));
+
+        let infos: Vec<SummarizedInfo> = captures.iter()
+            .zip(ends)
+            .map(|(caps, end)| {
+                let rest = &response[caps.get(0).unwrap().end()..end];
+
+                SummarizedInfo {
+                    title: String::from(&caps["title"]),
+                    summary: String::from(&caps["summary"]),
+                    quote: Regex::new(r"(?m)^Quote: (?<quote>.*)$").unwrap()
+                        .captures(rest)
+                        .map(|caps| String::from(caps["quote"].trim())),
+                    structure: self.parse_structure(rest),
+                }
+            })
+            .collect();
+
+        if infos.is_empty() {
+            return Err(Error::NoElementFound)
+        }
+
+        Ok(infos)
+    }
+
+    /// Parse a relation prediction response expected to follow the
+    /// `Relation: ...\nExplanation: ...` format, the explanation being left
+    /// empty if the model did not give one.
+    fn parse_predict_response(&self, response: &str) -> Result<PredictResponse, Error> {
+        let re = Regex::new(r"Relation: (?<relation>.*)").unwrap();
+
+        let Some(ref caps) = re.captures(response) else {
+            return Err(Error::NoElementFound)
+        };
+
+        let Some(relation_type) = caps.name("relation") else {
+            return Err(Error::MissingElement("Relation"))
+        };
+
+        let re = Regex::new(r"Explanation: (?<explanation>.*)").unwrap();
+
+        let explanation = re.captures(response)
+            .and_then(|caps| caps.name("explanation"))
+            .map(|explanation| String::from(explanation.as_str().trim()))
+            .unwrap_or_default();
+
+        Ok(PredictResponse {
+            relation: String::from(relation_type.as_str().trim()),
+            explanation,
+        })
+    }
+
+    /// Parse a relation prediction response expected to follow the
+    /// `Relation: ...\nExplanation: ...\nConfidence: ...` format.
+    fn parse_verbalized_predict_response(&self, response: &str) -> Result<VerbalizedPredictResponse, Error> {
+        let prediction = self.parse_predict_response(response)?;
+
+        let re = Regex::new(r"Confidence: (?<confidence>[0-9]*\.?[0-9]+)").unwrap();
+
+        let Some(confidence) = re.captures(response)
+            .and_then(|caps| caps.name("confidence"))
+            .and_then(|confidence| confidence.as_str().parse::<f32>().ok()) else {
+            return Err(Error::MissingElement("Confidence"))
+        };
+
+        Ok(VerbalizedPredictResponse {
+            prediction,
+            confidence,
+        })
+    }
+
+    fn relation_span(&self, response: &str) -> Option<Range<usize>> {
+        parsing::relation_span(response)
+    }
+
+    fn summarize_format(&self) -> String {
+        String::from("Title: <title>\nSummary: <summary>\nQuote: <passage of the post stating the argument, verbatim>\nClaim: <main claim>\nPremise: <premise> (one line per premise)\n\
+            Conclusion: <conclusion or warrant, if stated>\n(repeated for each argument of the post)")
+    }
+
+    fn predict_format(&self, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String {
+        let format = format!("Relation: <{}>\nExplanation: <explanation>", parsing::describe_labels(taxonomy));
+
+        match confidence {
+            ConfidenceCfg::Verbalized => format + "\nConfidence: <a number between 0.0 and 1.0>",
+            _ => format,
+        }
+    }
+
+    fn format_summarize_response(&self, infos: &[SummarizedInfo]) -> String {
+        infos.iter()
+            .map(|info| {
+                let mut lines = vec![format!("Title: {}", info.title), format!("Summary: {}", info.summary)];
+
+                lines.extend(info.quote.iter().map(|quote| format!("Quote: {}", quote)));
+
+                if let Some(ref structure) = info.structure {
+                    lines.push(format!("Claim: {}", structure.claim));
+                    lines.extend(structure.premises.iter().map(|premise| format!("Premise: {}", premise)));
+                    lines.extend(structure.conclusion.iter().map(|conclusion| format!("Conclusion: {}", conclusion)));
+                }
+
+                lines.join("\n")
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+
+    fn format_predict_response(&self, response: &PredictResponse) -> String {
+        format!("Relation: {}\nExplanation: {}", response.relation, response.explanation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_summarize_response {
+        use super::*;
+
+        #[test]
+        fn parses_title_and_summary() {
+            let infos = KeyValueParser.parse_summarize_response("Title: Taxes\nSummary: Taxes should be lower.")
+                .unwrap();
+
+            assert_eq!(infos[0].title, "Taxes");
+            assert_eq!(infos[0].summary, "Taxes should be lower.");
+        }
+
+        #[test]
+        fn parses_every_argument() {
+            let infos = KeyValueParser.parse_summarize_response(
+                "Title: Taxes\nSummary: Taxes should be lower.\n\nTitle: Spending\nSummary: Spending should be cut.",
+            ).unwrap();
+
+            assert_eq!(infos.len(), 2);
+            assert_eq!(infos[1].title, "Spending");
+        }
+
+        #[test]
+        fn parses_structure_of_each_argument() {
+            let infos = KeyValueParser.parse_summarize_response(
+                "Title: Taxes\nSummary: Taxes should be lower.\nClaim: Taxes should be lower.\n\
+                Premise: Households struggle.\nPremise: Growth is slow.\nConclusion: Lower taxes help.\n\n\
+                Title: Spending\nSummary: Spending should be cut.",
+            ).unwrap();
+
+            assert_eq!(infos[0].structure, Some(ArgumentStructure {
+                claim: String::from("Taxes should be lower."),
+                premises: vec![String::from("Households struggle."), String::from("Growth is slow.")],
+                conclusion: Some(String::from("Lower taxes help.")),
+            }));
+            assert_eq!(infos[1].structure, None);
+        }
+
+        #[test]
+        fn parses_quote_of_each_argument() {
+            let infos = KeyValueParser.parse_summarize_response(
+                "Title: Taxes\nSummary: Taxes should be lower.\nQuote: taxes are too high\n\n\
+                Title: Spending\nSummary: Spending should be cut.",
+            ).unwrap();
+
+            assert_eq!(infos[0].quote.as_deref(), Some("taxes are too high"));
+            assert_eq!(infos[1].quote, None);
+        }
+
+        #[test]
+        fn fails_without_elements() {
+            assert!(matches!(
+                KeyValueParser.parse_summarize_response("I cannot summarize this."),
+                Err(Error::NoElementFound),
+            ));
+        }
+    }
+
+    mod parse_predict_response {
+        use super::*;
+
+        #[test]
+        fn parses_relation() {
+            let predicted = KeyValueParser.parse_predict_response("Relation: Attack").unwrap();
+
+            assert_eq!(predicted.relation, "Attack");
+            assert_eq!(predicted.explanation, "");
+        }
+
+        #[test]
+        fn parses_explanation() {
+            let predicted = KeyValueParser.parse_predict_response(
+                "Relation: Support\nExplanation: Both posts argue for lower taxes.",
+            ).unwrap();
+
+            assert_eq!(predicted.relation, "Support");
+            assert_eq!(predicted.explanation, "Both posts argue for lower taxes.");
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::ops::Range;
+use regex::Regex;
+
+use crate::clients::llm::parsing::{self, Error, PredictResponse, ResponseParser, VerbalizedPredictResponse};
+use crate::configuration::settings::{ConfidenceCfg, TaxonomyCfg};
+use crate::models::{ArgumentStructure, SummarizedInfo};
+
+/// Parse responses whose elements are enclosed in XML-style tags (e.g.,
+/// `<title>...</title>`), ignoring any text outside of them.
+pub struct XmlTagsParser;
+
+impl XmlTagsParser {
+    /// Locate the trimmed content of the first `tag` element of a response.
+    fn element_span(&self, response: &str, tag: &str) -> Option<Range<usize>> {
+        let re = Regex::new(&format!(r"(?is)<{tag}>\s*(?<content>.*?)\s*</{tag}>")).unwrap();
+
+        re.captures(response)
+            .and_then(|caps| caps.name("content"))
+            .map(|content| content.range())
+    }
+
+    fn element<'a>(&self, response: &'a str, tag: &'static str) -> Option<&'a str> {
+        self.element_span(response, tag)
+            .map(|span| &response[span])
+    }
+
+    /// Parse the elements of a single argument, its structure being left out
+    /// without a `claim` element.
+    fn parse_argument(&self, argument: &str) -> Result<SummarizedInfo, Error> {
+        let Some(title) = self.element(argument, "title") else {
+            return Err(Error::MissingElement("title"))
+        };
+
+        let Some(summary) = self.element(argument, "summary") else {
+            return Err(Error::MissingElement("summary"))
+        };
+
+        let structure = self.element(argument, "claim")
+            .map(|claim| ArgumentStructure {
+                claim: String::from(claim),
+                premises: self.elements(argument, "premise").into_iter()
+                    .map(String::from)
+                    .collect(),
+                conclusion: self.element(argument, "conclusion").map(String::from),
+            });
+
+        Ok(SummarizedInfo {
+            title: String::from(title),
+            summary: String::from(summary),
+            quote: self.element(argument, "quote").map(String::from),
+            structure,
+        })
+    }
+
+    /// Return the trimmed content of every `tag` element of a response.
+    fn elements<'a>(&self, response: &'a str, tag: &'static str) -> Vec<&'a str> {
+        let re = Regex::new(&format!(r"(?is)<{tag}>\s*(?<content>.*?)\s*</{tag}>")).unwrap();
+
+        re.captures_iter(response)
+            .filter_map(|caps| caps.name("content"))
+            .map(|content| content.as_str())
+            .collect()
+    }
+}
+
+impl ResponseParser for XmlTagsParser {
+    /// Parse a summarization response, each argument starting at a `title`
+    /// element and running until the next one.
+    fn parse_summarize_response(&self, response: &str) -> Result<Vec<SummarizedInfo>, Error> {
+        let starts: Vec<usize> = response.match_indices("<title>")
+            .map(|(start, _)| start)
+            .collect();
+
+        if starts.is_empty() {
+            return match self.element(response, "summary") {
+                Some(_) => Err(Error::MissingElement("title")),
+                None => Err(Error::NoElementFound),
+            }
+        }
+
+        let ends = starts.iter()
+            .skip(1)
+            .copied()
+            .chain(std::iter::once(response.len()));
+
+        starts.iter()
+            .zip(ends)
+            .map(|(&start, end)| self.parse_argument(&response[start..end]))
+            .collect()
+    }
+
+    /// Parse a relation prediction response, the explanation being left empty
+    /// if the model did not give one.
+    fn parse_predict_response(&self, response: &str) -> Result<PredictResponse, Error> {
+        let Some(relation_type) = self.element(response, "relation") else {
+            return Err(Error::MissingElement("relation"))
+        };
+
+        Ok(PredictResponse {
+            relation: String::from(relation_type),
+            explanation: String::from(self.element(response, "explanation").unwrap_or_default()),
+        })
+    }
+
+    fn parse_verbalized_predict_response(&self, response: &str) -> Result<VerbalizedPredictResponse, Error> {
+        let prediction = self.parse_predict_response(response)?;
+
+        let Some(confidence) = self.element(response, "confidence")
+            .and_then(|confidence| confidence.parse::<f32>().ok()) else {
+            return Err(Error::MissingElement("confidence"))
+        };
+
+        Ok(VerbalizedPredictResponse {
+            prediction,
+            confidence,
+        })
+    }
+
+    fn relation_span(&self, response: &str) -> Option<Range<usize>> {
+        self.element_span(response, "relation")
+    }
+
+    fn summarize_format(&self) -> String {
+        String::from("<title>title</title>\n<summary>summary</summary>\n<quote>passage of the post stating the argument, verbatim</quote>\n<claim>main claim</claim>\n\
+            <premise>premise</premise> (one element per premise)\n<conclusion>conclusion or warrant, if stated</conclusion>\n\
+            (repeated for each argument of the post)")
+    }
+
+    fn predict_format(&self, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String {
+        let format = format!("<relation>{}</relation>\n<explanation>explanation</explanation>", parsing::describe_labels(taxonomy));
+
+        match confidence {
+            ConfidenceCfg::Verbalized => format + "\n<confidence>a number between 0.0 and 1.0</confidence>",
+            _ => format,
+        }
+    }
+
+    fn format_summarize_response(&self, infos: &[SummarizedInfo]) -> String {
+        infos.iter()
+            .map(|info| {
+                let mut elements = vec![
+                    format!("<title>{}</title>", info.title),
+                    format!("<summary>{}</summary>", info.summary),
+                ];
+
+                elements.extend(info.quote.iter().map(|quote| format!("<quote>{}</quote>", quote)));
+
+                if let Some(ref structure) = info.structure {
+                    elements.push(format!("<claim>{}</claim>", structure.claim));
+                    elements.extend(structure.premises.iter().map(|premise| format!("<premise>{}</premise>", premise)));
+                    elements.extend(structure.conclusion.iter().map(|conclusion| format!("<conclusion>{}</conclusion>", conclusion)));
+                }
+
+                elements.join("\n")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn format_predict_response(&self, response: &PredictResponse) -> String {
+        format!(
+            "<relation>{}</relation>\n<explanation>{}</explanation>",
+            response.relation, response.explanation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_summarize_response {
+        use super::*;
+
+        #[test]
+        fn parses_tags_among_text() {
+            let infos = XmlTagsParser.parse_summarize_response(
+                "Here is the summary:\n<title>Taxes</title>\n<summary>\nTaxes should be lower.\n</summary>",
+            ).unwrap();
+
+            assert_eq!(infos[0].title, "Taxes");
+            assert_eq!(infos[0].summary, "Taxes should be lower.");
+        }
+
+        #[test]
+        fn parses_structure_of_each_argument() {
+            let infos = XmlTagsParser.parse_summarize_response(
+                "<title>Taxes</title><summary>Taxes should be lower.</summary>\
+                <claim>Taxes should be lower.</claim><premise>Households struggle.</premise>\
+                <title>Spending</title><summary>Spending should be cut.</summary>",
+            ).unwrap();
+
+            assert_eq!(infos.len(), 2);
+            assert_eq!(infos[0].structure, Some(ArgumentStructure {
+                claim: String::from("Taxes should be lower."),
+                premises: vec![String::from("Households struggle.")],
+                conclusion: None,
+            }));
+            assert_eq!(infos[1].structure, None);
+        }
+
+        #[test]
+        fn reports_missing_tag() {
+            assert!(matches!(
+                XmlTagsParser.parse_summarize_response("<title>Taxes</title>"),
+                Err(Error::MissingElement("summary")),
+            ));
+        }
+    }
+
+    mod relation_span {
+        use super::*;
+
+        #[test]
+        fn locates_relation_content() {
+            let response = "<relation> Attack </relation>";
+
+            assert_eq!(&response[XmlTagsParser.relation_span(response).unwrap()], "Attack");
+        }
+    }
+}
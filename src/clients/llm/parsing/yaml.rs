@@ -0,0 +1,92 @@
+use std::ops::Range;
+use serde::de::DeserializeOwned;
+
+use crate::clients::llm::parsing::{self, Error, PredictResponse, ResponseParser, SummarizedArguments, SummarizeResponse, VerbalizedPredictResponse};
+use crate::configuration::settings::{ConfidenceCfg, TaxonomyCfg};
+use crate::models::SummarizedInfo;
+
+/// Parse responses written as a YAML mapping, possibly inside a Markdown code
+/// fence.
+pub struct YamlParser;
+
+impl YamlParser {
+    /// Deserialize a response, stripping its code fence if any.
+    fn parse<T: DeserializeOwned>(&self, response: &str) -> Result<T, Error> {
+        let document = response.trim()
+            .trim_start_matches("```yaml")
+            .trim_start_matches("```yml")
+            .trim_start_matches("```")
+            .trim_end_matches("```");
+
+        Ok(serde_yaml::from_str(document)?)
+    }
+}
+
+impl ResponseParser for YamlParser {
+    fn parse_summarize_response(&self, response: &str) -> Result<Vec<SummarizedInfo>, Error> {
+        self.parse::<SummarizedArguments>(response)
+            .map(|arguments| arguments.0)
+    }
+
+    fn parse_predict_response(&self, response: &str) -> Result<PredictResponse, Error> {
+        self.parse(response)
+    }
+
+    fn parse_verbalized_predict_response(&self, response: &str) -> Result<VerbalizedPredictResponse, Error> {
+        self.parse(response)
+    }
+
+    fn relation_span(&self, response: &str) -> Option<Range<usize>> {
+        parsing::relation_span(response)
+    }
+
+    fn summarize_format(&self) -> String {
+        String::from("arguments:\n  - title: <title>\n    summary: <summary>\n    quote: <passage of the post stating the argument, verbatim>\n    structure:\n      claim: <main claim>\n      \
+            premises:\n        - <premise>\n      conclusion: <conclusion or warrant, or null>")
+    }
+
+    fn predict_format(&self, confidence: &ConfidenceCfg, taxonomy: &TaxonomyCfg) -> String {
+        let format = format!("relation: <{}>\nexplanation: <explanation>", parsing::describe_labels(taxonomy));
+
+        match confidence {
+            ConfidenceCfg::Verbalized => format + "\nconfidence: <a number between 0.0 and 1.0>",
+            _ => format,
+        }
+    }
+
+    fn format_summarize_response(&self, infos: &[SummarizedInfo]) -> String {
+        serde_yaml::to_string(&SummarizeResponse { arguments: infos.to_vec() }).unwrap().trim_end().to_string()
+    }
+
+    fn format_predict_response(&self, response: &PredictResponse) -> String {
+        serde_yaml::to_string(response).unwrap().trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_verbalized_predict_response {
+        use super::*;
+
+        #[test]
+        fn parses_fenced_mapping() {
+            let predicted = YamlParser.parse_verbalized_predict_response(
+                "```yaml\nrelation: Support\nexplanation: Both posts argue for lower taxes.\nconfidence: 0.8\n```",
+            ).unwrap();
+
+            assert_eq!(predicted.prediction.relation, "Support");
+            assert_eq!(predicted.prediction.explanation, "Both posts argue for lower taxes.");
+            assert_eq!(predicted.confidence, 0.8);
+        }
+
+        #[test]
+        fn fails_on_missing_field() {
+            assert!(matches!(
+                YamlParser.parse_verbalized_predict_response("relation: Support\nexplanation: Agreed."),
+                Err(Error::InvalidYaml(_)),
+            ));
+        }
+    }
+}
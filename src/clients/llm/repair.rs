@@ -0,0 +1,141 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::clients::llm::parsing;
+
+tokio::task_local! {
+    /// Whether a response of the item being tracked was sent back for repair.
+    static NEEDED: Cell<bool>;
+}
+
+/// Count the items (e.g., the posts to summarize) with a response which
+/// could not be parsed and was sent back to the model for repair.
+#[derive(Default)]
+pub struct RepairCounter {
+    needed: AtomicUsize,
+    repaired: AtomicUsize,
+}
+
+/// A snapshot of the repairs of a run.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RepairStats {
+    /// The number of items with a response which could not be parsed at
+    /// first.
+    pub needed: usize,
+    /// The number of those items eventually processed successfully.
+    pub repaired: usize,
+}
+
+impl RepairCounter {
+    /// Process an item, recording it once if any of its responses needed
+    /// repair, whatever the number of inferences it took (e.g., chunks,
+    /// samples or parse retries).
+    pub async fn track<T, E>(&self, item: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let (result, needed) = NEEDED.scope(Cell::new(false), async {
+            let result = item.await;
+
+            (result, NEEDED.with(Cell::get))
+        }).await;
+
+        if needed {
+            self.record(result.is_ok());
+        }
+
+        result
+    }
+
+    /// Record an item which needed repair, and whether it was repaired.
+    fn record(&self, repaired: bool) {
+        self.needed.fetch_add(1, Ordering::Relaxed);
+
+        if repaired {
+            self.repaired.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> RepairStats {
+        RepairStats {
+            needed: self.needed.load(Ordering::Relaxed),
+            repaired: self.repaired.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Mark the item being tracked as having a response sent back for repair,
+/// doing nothing outside of [`RepairCounter::track`].
+pub fn mark_needed() {
+    let _ = NEEDED.try_with(|needed| needed.set(true));
+}
+
+/// Build the prompt asking the model to fix a response which could not be
+/// parsed, from the original prompt, the bad response, a description of the
+/// expected format and the parser error.
+pub fn repair_prompt(prompt: &str, response: &str, format: &str, error: &parsing::Error) -> String {
+    format!(
+        "{}\n\nYour previous answer was:\n{}\n\n{}",
+        prompt, response.trim(), repair_instruction(format, error),
+    )
+}
+
+/// Build the message asking the model to fix its last response in a
+/// conversation, from a description of the expected format and the parser
+/// error.
+pub fn repair_instruction(format: &str, error: &parsing::Error) -> String {
+    format!(
+        "It could not be read ({}). Answer again, following exactly this format:\n{}\n",
+        error, format,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod repair_prompt {
+        use super::*;
+
+        #[test]
+        fn includes_response_format_and_error() {
+            let prompt = repair_prompt(
+                "Summarize the argument.",
+                "I cannot summarize this.",
+                "Title: <title>\nSummary: <summary>",
+                &parsing::Error::NoElementFound,
+            );
+
+            assert!(prompt.starts_with("Summarize the argument."));
+            assert!(prompt.contains("I cannot summarize this."));
+            assert!(prompt.contains("Title: <title>\nSummary: <summary>"));
+            assert!(prompt.contains("no element found in LLM response"));
+        }
+    }
+
+    mod track {
+        use super::*;
+
+        #[tokio::test]
+        async fn records_item_once() {
+            let counter = RepairCounter::default();
+
+            let result: Result<(), ()> = counter.track(async {
+                mark_needed();
+                mark_needed();
+
+                Ok(())
+            }).await;
+
+            assert!(result.is_ok());
+            assert_eq!(counter.stats(), RepairStats { needed: 1, repaired: 1 });
+        }
+
+        #[tokio::test]
+        async fn ignores_items_without_repair() {
+            let counter = RepairCounter::default();
+
+            let _: Result<(), ()> = counter.track(async { Err(()) }).await;
+
+            assert_eq!(counter.stats(), RepairStats::default());
+        }
+    }
+}
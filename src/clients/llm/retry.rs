@@ -0,0 +1,114 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::configuration::settings::RetryCfg;
+
+/// Build the http client of a Large Language Model server with the configured
+/// timeouts.
+pub fn http_client(cfg: &RetryCfg) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(cfg.timeout_secs))
+        .connect_timeout(Duration::from_secs(cfg.connect_timeout_secs))
+        .build()
+        .unwrap()
+}
+
+/// Run `attempt` until it succeeds, fails with an error rejected by
+/// `is_retryable`, or has been retried `retries` times, waiting for an
+/// exponential backoff with jitter between attempts.
+pub async fn retry<T, E, F, Fut>(cfg: &RetryCfg, retries: u32, is_retryable: impl Fn(&E) -> bool, mut attempt: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut retried = 0;
+
+    loop {
+        match attempt().await {
+            Err(e) if retried < retries && is_retryable(&e) => {
+                let delay = backoff(cfg, retried);
+
+                log::warn!("{}, retrying in {:?} ({}/{})", e, delay, retried + 1, retries);
+
+                tokio::time::sleep(delay).await;
+                retried += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Draw the delay before the retry following `retried` previous retries, at
+/// random below an upper bound doubling on each retry ("full jitter").
+fn backoff(cfg: &RetryCfg, retried: u32) -> Duration {
+    let ceiling = cfg.initial_backoff_ms
+        .saturating_mul(2_u64.saturating_pow(retried))
+        .min(cfg.max_backoff_ms);
+
+    Duration::from_millis(fastrand::u64(0..=ceiling))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> RetryCfg {
+        RetryCfg {
+            initial_backoff_ms: 0,
+            max_backoff_ms: 0,
+            ..Default::default()
+        }
+    }
+
+    mod retry {
+        use super::*;
+
+        #[tokio::test]
+        async fn stops_after_max_retries() {
+            let mut attempts = 0;
+
+            let result: Result<(), String> = retry(&cfg(), 2, |_| true, || {
+                attempts += 1;
+
+                async { Err(String::from("server error")) }
+            }).await;
+
+            assert!(result.is_err());
+            assert_eq!(attempts, 3);
+        }
+
+        #[tokio::test]
+        async fn does_not_retry_fatal_errors() {
+            let mut attempts = 0;
+
+            let result: Result<(), String> = retry(&cfg(), 2, |_| false, || {
+                attempts += 1;
+
+                async { Err(String::from("invalid request")) }
+            }).await;
+
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+    }
+
+    mod backoff {
+        use super::*;
+
+        #[test]
+        fn is_capped() {
+            let cfg = RetryCfg {
+                initial_backoff_ms: 100,
+                max_backoff_ms: 1_000,
+                ..Default::default()
+            };
+
+            for retried in 0..40 {
+                assert!(backoff(&cfg, retried) <= Duration::from_millis(1_000));
+            }
+            assert!(backoff(&cfg, 0) <= Duration::from_millis(100));
+        }
+    }
+}
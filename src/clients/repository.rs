@@ -26,19 +26,23 @@ impl Repository {
 /// Trait defining all the Large Language Model client expected features for
 /// this program.
 pub trait RepositoryTrait {
-    /// Summarize the underlying argument of a user generated web-content
-    /// (e.g., Twitter Post)
-    async fn add_argument(&mut self, arg: models::Argument) -> Result<(), Error>;
+    /// Store a batch of posts, each linked to the arguments extracted from
+    /// it, in a single transaction.
+    async fn add_posts(&mut self, posts: Vec<models::ExtractedPost>) -> Result<(), Error>;
 
     async fn retrieve_argument(&mut self, arg_id: u32) -> Result<models::Argument, Error>;
 
-    async fn add_relation(&mut self, relation: models::Relation) -> Result<(), Error>;
+    /// Store a batch of relations in a single transaction.
+    async fn add_relations(&mut self, relations: Vec<models::Relation>) -> Result<(), Error>;
+
+    /// Retrieve the relations stored between the given arguments.
+    async fn retrieve_relations(&mut self, args_id: &[u32]) -> Result<Vec<models::Relation>, Error>;
 }
 
 impl RepositoryTrait for Repository {
-    async fn add_argument(&mut self, arg: models::Argument) -> Result<(), Error> {
+    async fn add_posts(&mut self, posts: Vec<models::ExtractedPost>) -> Result<(), Error> {
         match self {
-            Repository::Neo4j(client) => client.add_argument(arg)
+            Repository::Neo4j(client) => client.add_posts(posts)
                 .await,
         }
     }
@@ -50,9 +54,16 @@ impl RepositoryTrait for Repository {
         }
     }
 
-    async fn add_relation(&mut self, relation: models::Relation) -> Result<(), Error> {
+    async fn add_relations(&mut self, relations: Vec<models::Relation>) -> Result<(), Error> {
+        match self {
+            Repository::Neo4j(client) => client.add_relations(relations)
+                .await,
+        }
+    }
+
+    async fn retrieve_relations(&mut self, args_id: &[u32]) -> Result<Vec<models::Relation>, Error> {
         match self {
-            Repository::Neo4j(client) => client.add_relation(relation)
+            Repository::Neo4j(client) => client.retrieve_relations(args_id)
                 .await,
         }
     }
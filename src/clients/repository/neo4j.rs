@@ -5,6 +5,7 @@ use crate::models;
 use crate::clients::repository;
 
 #[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("neo4j error: {0}")]
     Neo4jError(#[from] neo4rs::Error),
@@ -13,7 +14,25 @@ pub enum Error {
     Neo4jDeError(#[from] neo4rs::DeError),
 
     #[error("no argument found for given request")]
-    NoArgumentFound
+    NoArgumentFound,
+
+    #[error("unknown relation type \"{0}\"")]
+    UnknownRelation(String),
+
+    #[error("node id {0} does not fit an argument id")]
+    InvalidId(i64),
+}
+
+/// Convert the id of a stored node to an argument id.
+fn argument_id(id: i64) -> Result<u32, Error> {
+    u32::try_from(id)
+        .map_err(|_| Error::InvalidId(id))
+}
+
+/// Read the type of a stored relation.
+fn relation_type(label: &str) -> Result<models::RelationType, Error> {
+    models::RelationType::parse(label)
+        .ok_or_else(|| Error::UnknownRelation(String::from(label)))
 }
 
 pub struct Neo4j {
@@ -31,7 +50,7 @@ impl Neo4j {
         let client = Graph::new(&cfg.uri, &cfg.user, &cfg.password).await?;
 
         Ok(Self {
-            client: client,
+            client,
             uri: cfg.uri.clone(),
             user: cfg.user.clone(),
             password: cfg.password.clone(),
@@ -40,16 +59,43 @@ impl Neo4j {
 }
 
 impl repository::RepositoryTrait for Neo4j {
-    async fn add_argument(&mut self, arg: models::Argument) -> Result<(), repository::Error> {
+    async fn add_posts(&mut self, posts: Vec<models::ExtractedPost>) -> Result<(), repository::Error> {
         let mut txn = self.client.start_txn().await
             .map_err(Error::from)?;
 
-        txn.run_queries([
-            query("CREATE (p:Argument {title: $title, summary: $summary, raw: $raw})")
-                .param("title", arg.summarized_info.title)
-                .param("summary", arg.summarized_info.summary)
-                .param("raw", arg.raw),
-        ]).await.map_err(Error::from)?;
+        txn.run_queries(posts.into_iter().map(|extracted| {
+            query("CREATE (p:Post {content: $content, author: $author, thread_context: $thread_context}) \
+                    WITH p \
+                    UNWIND range(0, size($titles) - 1) AS i \
+                    CREATE (a:Argument {title: $titles[i], summary: $summaries[i], raw: $raws[i], offset: $offsets[i], \
+                    claim: $claims[i], premises: $premises[i], conclusion: $conclusions[i]})-[:EXTRACTED_FROM]->(p)")
+                .param("content", extracted.post.content)
+                .param("author", extracted.post.author)
+                .param("thread_context", extracted.post.thread_context)
+                .param("titles", extracted.arguments.iter()
+                    .map(|arg| arg.summarized_info.title.clone())
+                    .collect::<Vec<String>>())
+                .param("summaries", extracted.arguments.iter()
+                    .map(|arg| arg.summarized_info.summary.clone())
+                    .collect::<Vec<String>>())
+                .param("raws", extracted.arguments.iter()
+                    .map(|arg| arg.raw.clone())
+                    .collect::<Vec<String>>())
+                .param("offsets", extracted.arguments.iter()
+                    .map(|arg| arg.offset.map(i64::from))
+                    .collect::<Vec<Option<i64>>>())
+                .param("claims", extracted.arguments.iter()
+                    .map(|arg| arg.summarized_info.structure.as_ref().map(|structure| structure.claim.clone()))
+                    .collect::<Vec<Option<String>>>())
+                .param("premises", extracted.arguments.iter()
+                    .map(|arg| arg.summarized_info.structure.as_ref()
+                        .map(|structure| structure.premises.clone())
+                        .unwrap_or_default())
+                    .collect::<Vec<Vec<String>>>())
+                .param("conclusions", extracted.arguments.iter()
+                    .map(|arg| arg.summarized_info.structure.as_ref().and_then(|structure| structure.conclusion.clone()))
+                    .collect::<Vec<Option<String>>>())
+        })).await.map_err(Error::from)?;
 
         txn.commit().await.map_err(Error::from)?;
 
@@ -63,17 +109,25 @@ impl repository::RepositoryTrait for Neo4j {
 
         let mut result = client.execute(query).await.unwrap();
 
-        while let Ok(Some(row)) = result.next().await {
+        if let Ok(Some(row)) = result.next().await {
             let node: neo4rs::Node = row.get("p").map_err(Error::from)?;
 
             return Ok(
                 models::Argument::with_id(
-                    node.id().try_into().unwrap(),
+                    argument_id(node.id())?,
                     models::SummarizedInfo{
                         title: node.get::<String>("title").map_err(Error::from)?,
                         summary: node.get::<String>("summary").map_err(Error::from)?,
+                        quote: None,
+                        structure: node.get::<String>("claim").ok()
+                            .map(|claim| models::ArgumentStructure {
+                                claim,
+                                premises: node.get::<Vec<String>>("premises").unwrap_or_default(),
+                                conclusion: node.get::<String>("conclusion").ok(),
+                            }),
                     },
                     node.get::<String>("raw").map_err(Error::from)?,
+                    node.get::<i64>("offset").ok().and_then(|offset| offset.try_into().ok()),
                 )
             )
         }
@@ -81,23 +135,85 @@ impl repository::RepositoryTrait for Neo4j {
         Err(repository::Error::Neo4j(Error::NoArgumentFound))
     }
 
-    async fn add_relation(&mut self, relation: models::Relation) -> Result<(), repository::Error> {
+    async fn add_relations(&mut self, relations: Vec<models::Relation>) -> Result<(), repository::Error> {
         let mut txn = self.client.start_txn().await
             .map_err(Error::from)?;
 
-        txn.run_queries([
+        txn.run_queries(relations.into_iter().map(|relation| {
             query(format!("MATCH (a:Argument), (b:Argument) \
                     WHERE ID(a) = $id_a AND ID(b) = $id_b \
-                    CREATE (a)-[:{}]->(b)", relation.relation_type.to_str()).as_str())
+                    CREATE (a)-[:{} {{confidence: $confidence, explanation: $explanation, \
+                    vote_models: $vote_models, vote_types: $vote_types, \
+                    vote_confidences: $vote_confidences, vote_explanations: $vote_explanations, \
+                    contradicted: $contradicted, created_at: datetime()}}]->(b)", relation.relation_type.to_str()).as_str())
                 .param("id_a", relation.arg_a_id)
                 .param("id_b", relation.arg_b_id)
-                //.param("confidence", relation.confidence.to_string())
-                //.param("explanation", relation.explanation),
-        ]).await.map_err(Error::from)?;
+                .param("confidence", relation.confidence)
+                .param("explanation", relation.explanation)
+                .param("vote_models", relation.votes.iter()
+                    .map(|vote| vote.model.clone())
+                    .collect::<Vec<String>>())
+                .param("vote_types", relation.votes.iter()
+                    .map(|vote| vote.relation_type.to_str().to_string())
+                    .collect::<Vec<String>>())
+                .param("vote_confidences", relation.votes.iter()
+                    .map(|vote| vote.confidence)
+                    .collect::<Vec<f32>>())
+                .param("vote_explanations", relation.votes.iter()
+                    .map(|vote| vote.explanation.clone())
+                    .collect::<Vec<String>>())
+                .param("contradicted", relation.contradicted)
+        })).await.map_err(Error::from)?;
 
         txn.commit().await.map_err(Error::from)?;
 
         Ok(())
     }
-}
 
+    async fn retrieve_relations(&mut self, args_id: &[u32]) -> Result<Vec<models::Relation>, repository::Error> {
+        let client = self.client.clone();
+        let query = query("MATCH (a:Argument)-[r]->(b:Argument) \
+                WHERE ID(a) IN $ids AND ID(b) IN $ids \
+                RETURN ID(a) AS id_a, ID(b) AS id_b, type(r) AS relation_type, \
+                coalesce(r.confidence, 1.0) AS confidence, \
+                coalesce(r.explanation, '') AS explanation, \
+                coalesce(r.vote_models, []) AS vote_models, \
+                coalesce(r.vote_types, []) AS vote_types, \
+                coalesce(r.vote_confidences, []) AS vote_confidences, \
+                coalesce(r.vote_explanations, []) AS vote_explanations, \
+                coalesce(r.contradicted, false) AS contradicted")
+            .param("ids", args_id.to_vec());
+
+        let mut result = client.execute(query).await.map_err(Error::from)?;
+        let mut relations = Vec::new();
+
+        while let Some(row) = result.next().await.map_err(Error::from)? {
+            let vote_models = row.get::<Vec<String>>("vote_models").map_err(Error::from)?;
+            let vote_types = row.get::<Vec<String>>("vote_types").map_err(Error::from)?;
+            let vote_confidences = row.get::<Vec<f64>>("vote_confidences").map_err(Error::from)?;
+            let vote_explanations = row.get::<Vec<String>>("vote_explanations").map_err(Error::from)?;
+
+            relations.push(models::Relation {
+                arg_a_id: argument_id(row.get::<i64>("id_a").map_err(Error::from)?)?,
+                arg_b_id: argument_id(row.get::<i64>("id_b").map_err(Error::from)?)?,
+                relation_type: relation_type(&row.get::<String>("relation_type").map_err(Error::from)?)?,
+                confidence: row.get::<f64>("confidence").map_err(Error::from)? as f32,
+                explanation: row.get::<String>("explanation").map_err(Error::from)?,
+                votes: vote_models.into_iter()
+                    .zip(vote_types.iter())
+                    .zip(vote_confidences)
+                    .zip(vote_explanations)
+                    .map(|(((model, vote_type), confidence), explanation)| Ok(models::Vote {
+                        model,
+                        relation_type: relation_type(vote_type)?,
+                        confidence: confidence as f32,
+                        explanation,
+                    }))
+                    .collect::<Result<Vec<models::Vote>, Error>>()?,
+                contradicted: row.get::<bool>("contradicted").map_err(Error::from)?,
+            });
+        }
+
+        Ok(relations)
+    }
+}
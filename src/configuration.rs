@@ -1,5 +1,6 @@
 pub mod cli;
 pub mod settings;
+pub mod template;
 
 pub use cli::*;
 pub use settings::*;
@@ -10,6 +10,10 @@ pub struct Cli {
     pub command: Option<Commands>,
     #[command(flatten)]
     pub log: Log,
+    #[command(flatten)]
+    pub concurrency: Concurrency,
+    #[command(flatten)]
+    pub metrics: Metrics,
     #[arg(long = "config")]
     #[arg(help = "The path to a configuration file for the client")]
     pub cfg_file_path: Option<String>,
@@ -22,6 +26,20 @@ pub struct Log {
     pub level: Option<String>,
 }
 
+#[derive(Args)]
+pub struct Concurrency {
+    #[arg(long = "max-requests", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    #[arg(help = "The maximum number of Large Language Model requests running at once")]
+    pub max_requests: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct Metrics {
+    #[arg(long = "metrics-output")]
+    #[arg(help = "The path of a JSON file to write the inference metrics of the run to")]
+    pub output: Option<PathBuf>,
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     #[clap(alias("summarize"))]
@@ -36,6 +54,8 @@ pub enum Commands {
         #[arg(short, long)]
         #[arg(help = "")]
         prompt: Option<String>,
+        #[command(flatten)]
+        options: ModelOptionsArgs,
     },
 
     #[clap(alias("predict"))]
@@ -50,5 +70,37 @@ pub enum Commands {
         #[arg(long)]
         #[arg(help = "")]
         prompt: Option<String>,
+        #[command(flatten)]
+        options: ModelOptionsArgs,
     }
 }
+
+/// Describe the inference options overriding the configured ones for the
+/// subcommand task.
+#[derive(Args)]
+pub struct ModelOptionsArgs {
+    #[arg(long)]
+    #[arg(help = "The temperature of the inference")]
+    pub temperature: Option<f64>,
+    #[arg(long)]
+    #[arg(help = "The cumulative probability threshold of the nucleus sampling")]
+    pub top_p: Option<f64>,
+    #[arg(long)]
+    #[arg(help = "The number of most likely tokens to sample from")]
+    pub top_k: Option<u32>,
+    #[arg(long)]
+    #[arg(help = "The random seed of the inference")]
+    pub seed: Option<i64>,
+    #[arg(long)]
+    #[arg(help = "The size of the context window")]
+    pub num_ctx: Option<u32>,
+    #[arg(long)]
+    #[arg(help = "The maximum number of tokens to generate")]
+    pub num_predict: Option<i32>,
+    #[arg(long)]
+    #[arg(help = "The penalty applied to repeated tokens")]
+    pub repeat_penalty: Option<f64>,
+    #[arg(long, num_args = 1..)]
+    #[arg(help = "The sequences stopping the generation")]
+    pub stop: Option<Vec<String>>,
+}
@@ -1,7 +1,10 @@
 use config::{ConfigBuilder, ConfigError, File, FileFormat};
 use config::builder::DefaultState;
-use serde::Deserialize;
-use crate::configuration::Cli;
+use std::path::PathBuf;
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::configuration::{template, Cli, ModelOptionsArgs};
+use crate::models;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -9,6 +12,12 @@ pub struct Settings {
     pub llm: LLMCfg,
     pub repository: RepositoryCfg,
     pub prompts: Prompts,
+    #[serde(default)]
+    pub prediction: PredictionCfg,
+    #[serde(default)]
+    pub concurrency: ConcurrencyCfg,
+    #[serde(default)]
+    pub metrics: MetricsCfg,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -16,15 +25,612 @@ pub struct Log {
     pub level: String,
 }
 
+// Loaded once per run, so the size of the Ollama variant does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Deserialize, Clone)]
 pub enum LLMCfg {
-    Ollama(OllamaCfg)
+    Ollama(OllamaCfg),
+    OpenAICompatible(OpenAICompatibleCfg),
+    Ensemble(EnsembleCfg),
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct OllamaCfg {
     pub uri: String,
     pub model: String,
+    /// The model used to compute embeddings, defaulting to `model`.
+    pub embedding_model: Option<String>,
+    /// Whether to constrain responses with a JSON schema (Ollama structured
+    /// outputs), falling back to free text parsing when disabled or when the
+    /// response is not valid JSON.
+    #[serde(default = "default_structured_outputs")]
+    pub structured_outputs: bool,
+    #[serde(default)]
+    pub retry: RetryCfg,
+    /// The context window guard, its window defaulting to the `num_ctx`
+    /// option shared by every task.
+    #[serde(default)]
+    pub context: ContextCfg,
+    /// The inference options shared by every task.
+    #[serde(default)]
+    pub options: ModelOptions,
+    /// The inference options overriding the shared ones for summarization.
+    #[serde(default)]
+    pub summarize_options: ModelOptions,
+    /// The inference options overriding the shared ones for relation
+    /// prediction.
+    #[serde(default)]
+    pub predict_options: ModelOptions,
+}
+
+fn default_structured_outputs() -> bool {
+    true
+}
+
+/// The model parameters of an inference, as listed in the documentation of the
+/// Ollama Modelfile
+/// (https://github.com/ollama/ollama/blob/main/docs/modelfile.md#valid-parameters-and-values).
+/// Unset parameters are left to the model defaults.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct ModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+impl ModelOptions {
+    /// Merge two sets of options, the parameters set in `self` taking
+    /// precedence over the ones of `base`.
+    pub fn merge(self, base: ModelOptions) -> ModelOptions {
+        ModelOptions {
+            temperature: self.temperature.or(base.temperature),
+            top_p: self.top_p.or(base.top_p),
+            top_k: self.top_k.or(base.top_k),
+            seed: self.seed.or(base.seed),
+            num_ctx: self.num_ctx.or(base.num_ctx),
+            num_predict: self.num_predict.or(base.num_predict),
+            repeat_penalty: self.repeat_penalty.or(base.repeat_penalty),
+            stop: self.stop.or(base.stop),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == ModelOptions::default()
+    }
+}
+
+impl From<&ModelOptionsArgs> for ModelOptions {
+    fn from(args: &ModelOptionsArgs) -> Self {
+        ModelOptions {
+            temperature: args.temperature,
+            top_p: args.top_p,
+            top_k: args.top_k,
+            seed: args.seed,
+            num_ctx: args.num_ctx,
+            num_predict: args.num_predict,
+            repeat_penalty: args.repeat_penalty,
+            stop: args.stop.clone(),
+        }
+    }
+}
+
+impl LLMCfg {
+    /// Override the configured summarization inference options with the given
+    /// ones.
+    pub fn override_summarize_options(&mut self, options: ModelOptions) {
+        match self {
+            LLMCfg::Ollama(cfg) =>
+                cfg.summarize_options = options.merge(cfg.summarize_options.clone()),
+            LLMCfg::OpenAICompatible(cfg) =>
+                cfg.summarize_options = options.merge(cfg.summarize_options.clone()),
+            LLMCfg::Ensemble(cfg) => cfg.members.iter_mut()
+                .for_each(|member| member.llm.override_summarize_options(options.clone())),
+        }
+    }
+
+    /// Override the configured relation prediction inference options with the
+    /// given ones.
+    pub fn override_predict_options(&mut self, options: ModelOptions) {
+        match self {
+            LLMCfg::Ollama(cfg) =>
+                cfg.predict_options = options.merge(cfg.predict_options.clone()),
+            LLMCfg::OpenAICompatible(cfg) =>
+                cfg.predict_options = options.merge(cfg.predict_options.clone()),
+            LLMCfg::Ensemble(cfg) => cfg.members.iter_mut()
+                .for_each(|member| member.llm.override_predict_options(options.clone())),
+        }
+    }
+
+    /// The context guard of the summarization, made by the first member of an
+    /// ensemble.
+    pub fn summary_context(&self) -> &ContextCfg {
+        match self {
+            LLMCfg::Ollama(cfg) => &cfg.context,
+            LLMCfg::OpenAICompatible(cfg) => &cfg.context,
+            LLMCfg::Ensemble(cfg) => cfg.members[0].llm.summary_context(),
+        }
+    }
+
+    /// The name of the configured model, or of every model of an ensemble.
+    pub fn model_name(&self) -> String {
+        match self {
+            LLMCfg::Ollama(cfg) => cfg.model.clone(),
+            LLMCfg::OpenAICompatible(cfg) => cfg.model.clone(),
+            LLMCfg::Ensemble(cfg) => cfg.members.iter()
+                .map(|member| member.llm.model_name())
+                .collect::<Vec<String>>()
+                .join(","),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            LLMCfg::Ensemble(cfg) if cfg.members.is_empty() =>
+                Err(ConfigError::Message(String::from("llm ensemble must have at least one member"))),
+            LLMCfg::Ensemble(cfg) if cfg.members.iter().any(|member| matches!(member.llm, LLMCfg::Ensemble(_))) =>
+                Err(ConfigError::Message(String::from("llm ensemble members cannot be ensembles themselves"))),
+            LLMCfg::Ensemble(cfg) => cfg.members.iter()
+                .try_for_each(|member| member.llm.validate()),
+            LLMCfg::Ollama(cfg) => cfg.context.validate(),
+            LLMCfg::OpenAICompatible(cfg) => cfg.context.validate(),
+        }
+    }
+}
+
+/// Configuration of several models whose relation predictions are combined
+/// by vote. The first member is used for everything else: summarization and
+/// the embeddings of the pre-filter and of the few-shot example selection.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EnsembleCfg {
+    pub members: Vec<EnsembleMemberCfg>,
+    #[serde(default)]
+    pub voting: VotingCfg,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EnsembleMemberCfg {
+    pub llm: LLMCfg,
+    /// The weight of the member votes with the `Weighted` voting method.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// The method used to combine the relations predicted by the members of an
+/// ensemble into one relation.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum VotingCfg {
+    /// The most predicted relation is kept, with the share of members
+    /// predicting it as confidence.
+    #[default]
+    Majority,
+    /// Each member vote counts as its weight times its confidence, the
+    /// relation with the highest total is kept with its share of the total as
+    /// confidence.
+    Weighted,
+    /// A relation is only kept if every member predicted it.
+    Unanimous,
+}
+
+/// Configuration of a server exposing an OpenAI-compatible
+/// `/v1/chat/completions` endpoint (e.g., vLLM, llama.cpp server).
+#[derive(Debug, Deserialize, Clone)]
+pub struct OpenAICompatibleCfg {
+    /// The base URL of the server, without the `/v1` suffix.
+    pub uri: String,
+    pub model: String,
+    /// The model used to compute embeddings, defaulting to `model`.
+    pub embedding_model: Option<String>,
+    /// The key sent as a bearer token, if the server requires one.
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub retry: RetryCfg,
+    #[serde(default)]
+    pub context: ContextCfg,
+    /// The inference options shared by every task, among which the server
+    /// only supports `temperature`, `top_p`, `seed`, `num_predict` and `stop`.
+    #[serde(default)]
+    pub options: ModelOptions,
+    /// The inference options overriding the shared ones for summarization.
+    #[serde(default)]
+    pub summarize_options: ModelOptions,
+    /// The inference options overriding the shared ones for relation
+    /// prediction.
+    #[serde(default)]
+    pub predict_options: ModelOptions,
+}
+
+/// The guard checking that the prompts sent to a model fit in its context
+/// window, their number of tokens being estimated from their length.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContextCfg {
+    /// The size of the context window of the model in tokens. Ollama models
+    /// default to the `num_ctx` option of each task. The guard is disabled
+    /// when the window is unknown.
+    #[serde(default)]
+    pub window_tokens: Option<usize>,
+    /// The number of tokens of the window kept free for the response.
+    #[serde(default = "default_response_tokens")]
+    pub response_tokens: usize,
+    /// The average number of characters per token of the model tokenizer.
+    #[serde(default = "default_chars_per_token")]
+    pub chars_per_token: f32,
+    #[serde(default)]
+    pub overflow: OverflowCfg,
+    /// The maximum number of tokens of content of a chunk, with the `Chunk`
+    /// and `MapReduce` overflow handling, defaulting to as much as fits in the
+    /// window.
+    #[serde(default)]
+    pub chunk_tokens: Option<usize>,
+    /// The number of tokens of content repeated at the start of a chunk from
+    /// the end of the previous one.
+    #[serde(default)]
+    pub overlap_tokens: usize,
+}
+
+fn default_response_tokens() -> usize {
+    512
+}
+
+fn default_chars_per_token() -> f32 {
+    4.0
+}
+
+impl ContextCfg {
+    fn validate(&self) -> Result<(), ConfigError> {
+        match self.chunk_tokens {
+            Some(chunk_tokens) if self.overlap_tokens >= chunk_tokens =>
+                Err(ConfigError::Message(String::from("context overlap_tokens must be lower than chunk_tokens"))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for ContextCfg {
+    fn default() -> Self {
+        ContextCfg {
+            window_tokens: None,
+            response_tokens: default_response_tokens(),
+            chars_per_token: default_chars_per_token(),
+            overflow: OverflowCfg::default(),
+            chunk_tokens: None,
+            overlap_tokens: 0,
+        }
+    }
+}
+
+/// The handling of a prompt exceeding the context window.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub enum OverflowCfg {
+    /// The prompt is not sent and the post or pair of arguments is skipped.
+    #[default]
+    Reject,
+    /// The content of the post, or of both arguments, is cut to fit, with a
+    /// warning.
+    Truncate,
+    /// The post is split into chunks summarized separately. Relation
+    /// predictions are truncated instead, as a pair of arguments cannot be
+    /// split.
+    Chunk,
+    /// The post is split into chunks summarized separately, whose partial
+    /// summaries are then merged into one argument by the `reduce` prompt.
+    /// Relation predictions are truncated instead.
+    MapReduce,
+}
+
+/// The timeouts of the http requests to a Large Language Model server, and
+/// the policy for retrying the failed ones.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryCfg {
+    /// The maximum duration of a request, from connection to the end of the
+    /// response body.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// The maximum duration of the connection to the server.
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// The maximum number of retries of a request failing with a transient
+    /// error (e.g., a timeout, a connection reset or a 5xx status).
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// The maximum number of retries of an inference whose response could not
+    /// be parsed.
+    #[serde(default = "default_parse_retries")]
+    pub parse_retries: u32,
+    /// The maximum number of times a response which could not be parsed is
+    /// sent back to the model along with the expected format and the parser
+    /// error, before falling back to `parse_retries`.
+    #[serde(default)]
+    pub repair_attempts: u32,
+    /// The upper bound of the delay before the first retry, doubled on each
+    /// further retry. The actual delay is drawn at random below this bound.
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// The maximum upper bound of the delay before a retry.
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    300
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_parse_retries() -> u32 {
+    1
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    30_000
+}
+
+impl Default for RetryCfg {
+    fn default() -> Self {
+        RetryCfg {
+            timeout_secs: default_timeout_secs(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            max_retries: default_max_retries(),
+            parse_retries: default_parse_retries(),
+            repair_attempts: 0,
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+}
+
+/// Configuration of the argument relations prediction.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PredictionCfg {
+    #[serde(default)]
+    pub confidence: ConfidenceCfg,
+    #[serde(default)]
+    pub strategy: PredictionStrategyCfg,
+    #[serde(default)]
+    pub consistency: ConsistencyCfg,
+    /// Whether to skip the prediction of the relation of `b` toward `a` when
+    /// `a` was predicted as not related to `b`.
+    #[serde(default)]
+    pub symmetric_screening: bool,
+    #[serde(default)]
+    pub pre_filter: PreFilterCfg,
+    /// Whether to skip the pairs of arguments with a relation already stored
+    /// between them, to resume an interrupted prediction without duplicating
+    /// its relations.
+    #[serde(default)]
+    pub skip_stored: bool,
+    #[serde(default)]
+    pub taxonomy: TaxonomyCfg,
+    /// Labels used by a model or a dataset for a relation (e.g., `Neutral:
+    /// NotRelated`), checked before the labels of the relations themselves.
+    /// Keyed by their normalized label once the settings are loaded.
+    #[serde(default)]
+    pub label_aliases: HashMap<String, models::RelationType>,
+}
+
+impl PredictionCfg {
+    /// Key the label aliases by their normalized label and the taxonomy
+    /// mapping by the label of its relations, failing on unknown relations
+    /// and on labels standing for the same one.
+    fn normalize_labels(&mut self) -> Result<(), ConfigError> {
+        self.label_aliases = normalize_keys(&self.label_aliases, "label alias", |label| Ok(models::normalize_label(label)))?;
+
+        if let TaxonomyCfg::FineToCoarse { ref mut mapping } = self.taxonomy {
+            *mapping = normalize_keys(mapping, "taxonomy mapping", |label| {
+                models::RelationType::parse(label)
+                    .map(|relation_type| String::from(relation_type.to_str()))
+                    .ok_or_else(|| ConfigError::Message(format!("unknown relation \"{}\" in taxonomy mapping", label)))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rekey the labels of a configuration map, in label order so that a
+/// collision is always reported alike.
+fn normalize_keys(
+    labels: &HashMap<String, models::RelationType>,
+    kind: &str,
+    normalize: impl Fn(&str) -> Result<String, ConfigError>,
+) -> Result<HashMap<String, models::RelationType>, ConfigError> {
+    let mut sorted: Vec<_> = labels.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut normalized: HashMap<String, (&String, models::RelationType)> = HashMap::with_capacity(labels.len());
+
+    for (label, relation_type) in sorted {
+        let key = normalize(label)?;
+
+        if let Some((other, _)) = normalized.get(&key) {
+            return Err(ConfigError::Message(format!(
+                "{} labels \"{}\" and \"{}\" both stand for \"{}\"", kind, other, label, key,
+            )))
+        }
+
+        normalized.insert(key, (label, relation_type.clone()));
+    }
+
+    Ok(normalized.into_iter()
+        .map(|(key, (_, relation_type))| (key, relation_type))
+        .collect())
+}
+
+/// The relations the model is asked to choose from, and how they are mapped
+/// before being stored.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum TaxonomyCfg {
+    /// The coarse three-way scheme: `Support`, `Attack` or `NotRelated`.
+    #[default]
+    Coarse,
+    /// The finer-grained relations (`PartialSupport`, `Rebut`, `Undercut` and
+    /// `Undermine` along with `Support` and `NotRelated`), stored as predicted.
+    Fine,
+    /// The finer-grained relations, mapped onto the coarse scheme before being
+    /// stored. The relations missing from `mapping` are mapped to the coarse
+    /// relation they belong to (e.g., `Rebut` to `Attack`). Keyed by the label
+    /// of the relations once the settings are loaded.
+    FineToCoarse {
+        #[serde(default)]
+        mapping: HashMap<String, models::RelationType>,
+    },
+}
+
+impl TaxonomyCfg {
+    /// Return the relations the model is asked to choose from.
+    pub fn labels(&self) -> &'static [models::RelationType] {
+        match self {
+            TaxonomyCfg::Coarse => &models::RelationType::COARSE,
+            TaxonomyCfg::Fine | TaxonomyCfg::FineToCoarse { .. } => &models::RelationType::FINE,
+        }
+    }
+
+    /// Map a predicted relation to the relation to store.
+    pub fn map(&self, relation_type: models::RelationType) -> models::RelationType {
+        match self {
+            TaxonomyCfg::Coarse => relation_type.coarse(),
+            TaxonomyCfg::Fine => relation_type,
+            TaxonomyCfg::FineToCoarse { mapping } => mapping.get(relation_type.to_str())
+                .cloned()
+                .unwrap_or_else(|| relation_type.coarse()),
+        }
+    }
+}
+
+/// The selection of the pairs of arguments sent to relation prediction, based
+/// on the similarity of the embeddings of their summaries.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum PreFilterCfg {
+    /// Every pair of arguments is sent to prediction.
+    #[default]
+    None,
+    /// Only the pairs made of an argument and one of its `k` most similar
+    /// arguments are sent to prediction.
+    TopK {
+        k: usize,
+    },
+    /// Only the pairs of arguments with a cosine similarity of at least
+    /// `similarity` are sent to prediction.
+    Threshold {
+        similarity: f32,
+    },
+}
+
+/// The handling of the relations of a pair of arguments contradicting each
+/// other, one supporting the other argument while the other attacks it.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum ConsistencyCfg {
+    /// Contradictory relations are stored as predicted.
+    #[default]
+    None,
+    /// Contradictory relations are flagged as such.
+    Flag,
+    /// Contradictory relations are flagged and their confidence is multiplied
+    /// by `factor`.
+    Penalize {
+        factor: f32,
+    },
+    /// Both relations are predicted again with the `tie_break` prompt, and
+    /// flagged if they still contradict each other.
+    TieBreak,
+}
+
+/// The strategy used to predict the relation of a pair of arguments.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum PredictionStrategyCfg {
+    /// A single prediction with the configured inference options.
+    #[default]
+    Greedy,
+    /// The relation is predicted `samples` times at the given `temperature`,
+    /// the majority relation is kept with the share of samples agreeing with
+    /// it as confidence, and every sample is kept as a vote. Failed samples
+    /// are left out, the pair being skipped only if all of them fail.
+    SelfConsistency {
+        samples: usize,
+        temperature: f64,
+    },
+}
+
+/// The method used to estimate the confidence of a single predicted relation.
+/// With the `SelfConsistency` strategy it only applies to the individual
+/// votes.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum ConfidenceCfg {
+    /// No estimation, every relation is given a confidence of 1.0.
+    #[default]
+    Constant,
+    /// The model is asked for a numeric confidence between 0.0 and 1.0
+    /// alongside the relation (as a `Confidence: ...` line when structured
+    /// outputs are disabled).
+    Verbalized,
+    /// The confidence is the probability of the tokens of the relation label,
+    /// as exposed by the backend token log-probabilities.
+    LogProbs,
+}
+
+/// Configuration of the concurrent processing of arguments by the
+/// subcommands.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConcurrencyCfg {
+    /// The maximum number of Large Language Model requests running at once.
+    #[serde(default = "default_max_requests")]
+    pub max_requests: usize,
+    /// The number of results written to the repository in a single
+    /// transaction.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_max_requests() -> usize {
+    1
+}
+
+fn default_batch_size() -> usize {
+    32
+}
+
+impl Default for ConcurrencyCfg {
+    fn default() -> Self {
+        ConcurrencyCfg {
+            max_requests: default_max_requests(),
+            batch_size: default_batch_size(),
+        }
+    }
+}
+
+/// Configuration of the inference metrics reported at the end of the
+/// subcommands.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MetricsCfg {
+    /// The path of the JSON file the metrics are written to, in addition to
+    /// being printed.
+    pub output: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -43,12 +649,214 @@ pub struct Neo4jCfg {
 pub struct Prompts {
     pub summary: Prompt,
     pub predict: Prompt,
+    /// The prompt used to predict again contradictory relations.
+    #[serde(default)]
+    pub tie_break: Option<Prompt>,
+    /// The prompt merging the partial summaries of the chunks of a post into
+    /// one summarized argument, with the `MapReduce` overflow handling.
+    #[serde(default)]
+    pub reduce: Option<Prompt>,
+    /// The library of labeled examples injected in the prompts through their
+    /// `{{examples}}` placeholder.
+    #[serde(default)]
+    pub examples: Option<ExamplesCfg>,
+}
+
+/// Configuration of the few-shot demonstrations of the prompts.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExamplesCfg {
+    /// The path to the JSON file of labeled examples, with a list of
+    /// `summarize` examples (`content`, `title`, `summary`) and a list of
+    /// `predict` examples (`arg_a`, `arg_b`, `relation`, `explanation`).
+    pub file: PathBuf,
+    /// The number of examples injected in each prompt.
+    #[serde(default = "default_examples_count")]
+    pub count: usize,
+    #[serde(default)]
+    pub selection: ExampleSelectionCfg,
+    /// The seed of the `Random` selection, drawn at random if not set.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn default_examples_count() -> usize {
+    3
+}
+
+/// The method used to select the examples injected in a prompt.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum ExampleSelectionCfg {
+    /// The first examples of the library, for every prompt.
+    #[default]
+    Fixed,
+    /// Examples sampled at random for each prompt.
+    Random,
+    /// The examples whose embeddings are the most similar to the input of the
+    /// prompt.
+    MostSimilar,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Prompt {
     pub system: Option<String>,
+    /// The template of the prompt, whose `{{name}}` placeholders are replaced
+    /// by the content of the arguments (e.g., `{{raw}}` for a summarization,
+    /// `{{arg_a.summary}}` for a relation prediction).
     pub prompt: String,
+    /// The format the prompt asks the model to answer in, selecting the
+    /// parser of its responses.
+    #[serde(default)]
+    pub format: ResponseFormatCfg,
+    /// The fields of the arguments given to a relation prediction prompt
+    /// through its `{{arg_a}}` and `{{arg_b}}` placeholders.
+    #[serde(default)]
+    pub arguments: ArgumentFieldsCfg,
+    #[serde(default)]
+    pub endpoint: EndpointCfg,
+}
+
+/// The Ollama endpoint a prompt is sent to. OpenAI-compatible servers are
+/// always sent a conversation.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub enum EndpointCfg {
+    /// `/api/generate`: a single prompt, preceded by its few-shot
+    /// demonstrations.
+    #[default]
+    Generate,
+    /// `/api/chat`: a conversation whose few-shot demonstrations are previous
+    /// turns, continued when a response is sent back for repair.
+    Chat,
+}
+
+/// The representation of an argument given to a relation prediction prompt.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub enum ArgumentFieldsCfg {
+    /// The raw content of the post.
+    #[default]
+    Raw,
+    /// The summary of the argument.
+    Summary,
+    /// The title of the argument followed by its summary.
+    TitleSummary,
+    /// The raw content of the post followed by the summary of its argument.
+    RawSummary,
+}
+
+impl ArgumentFieldsCfg {
+    /// Represent an argument with the selected fields.
+    pub fn represent(&self, arg: &models::Argument) -> String {
+        match self {
+            ArgumentFieldsCfg::Raw => arg.raw.clone(),
+            ArgumentFieldsCfg::Summary => arg.summarized_info.summary.clone(),
+            ArgumentFieldsCfg::TitleSummary =>
+                format!("{}\n{}", arg.summarized_info.title, arg.summarized_info.summary),
+            ArgumentFieldsCfg::RawSummary =>
+                format!("{}\n(Summary: {})", arg.raw, arg.summarized_info.summary),
+        }
+    }
+}
+
+impl Prompt {
+    /// Check that every placeholder of the prompt is one of `allowed`. With
+    /// few-shot examples, a prompt with placeholders must also have an
+    /// `{{examples}}` placeholder, as they would be silently dropped
+    /// otherwise.
+    pub fn validate(&self, allowed: &'static [&'static str], with_examples: bool) -> Result<(), template::Error> {
+        template::validate(&self.prompt, allowed)?;
+
+        let placeholders = template::placeholders(&self.prompt)?;
+
+        if with_examples && !placeholders.is_empty() && !placeholders.contains(&"examples") {
+            return Err(template::Error::MissingPlaceholder("examples"))
+        }
+
+        Ok(())
+    }
+
+    /// Render the prompt of the summarization of `post` with the given
+    /// few-shot demonstrations. A prompt without any placeholder is followed
+    /// by the demonstrations and the post content.
+    pub fn render_summarize(&self, post: &models::Post, examples: &str) -> String {
+        let suffix = if examples.is_empty() {
+            "\n\nArg:{{raw}}\n"
+        } else {
+            "\n\n{{examples}}\n\nArg:{{raw}}\n"
+        };
+
+        template::render(
+            &self.template_or(suffix),
+            &[
+                ("examples", examples),
+                ("raw", &post.content),
+                ("author", post.author.as_deref().unwrap_or_default()),
+                ("thread_context", post.thread_context.as_deref().unwrap_or_default()),
+            ],
+        )
+    }
+
+    /// Render the prompt of the prediction of the relation of `arg_a` toward
+    /// `arg_b` with the given few-shot demonstrations. A prompt without any
+    /// placeholder is followed by the demonstrations and the selected fields
+    /// of both arguments.
+    pub fn render_predict(&self, arg_a: &models::Argument, arg_b: &models::Argument, examples: &str) -> String {
+        let suffix = if examples.is_empty() {
+            "\n\nArg1:{{arg_a}}\nArg2:{{arg_b}}\n"
+        } else {
+            "\n\n{{examples}}\n\nArg1:{{arg_a}}\nArg2:{{arg_b}}\n"
+        };
+
+        template::render(
+            &self.template_or(suffix),
+            &[
+                ("examples", examples),
+                ("arg_a", &self.arguments.represent(arg_a)),
+                ("arg_b", &self.arguments.represent(arg_b)),
+                ("arg_a.raw", &arg_a.raw),
+                ("arg_a.title", &arg_a.summarized_info.title),
+                ("arg_a.summary", &arg_a.summarized_info.summary),
+                ("arg_b.raw", &arg_b.raw),
+                ("arg_b.title", &arg_b.summarized_info.title),
+                ("arg_b.summary", &arg_b.summarized_info.summary),
+            ],
+        )
+    }
+
+    /// Render the prompt merging the partial `summaries` of the chunks of
+    /// `post`, written in the response format. A prompt without any
+    /// placeholder is followed by the summaries.
+    pub fn render_reduce(&self, post: &models::Post, summaries: &str) -> String {
+        template::render(
+            &self.template_or("\n\n{{summaries}}\n"),
+            &[
+                ("summaries", summaries),
+                ("author", post.author.as_deref().unwrap_or_default()),
+                ("thread_context", post.thread_context.as_deref().unwrap_or_default()),
+            ],
+        )
+    }
+
+    /// Return the template of the prompt, followed by `suffix` if it has no
+    /// placeholder, as prompts were written before templating.
+    fn template_or(&self, suffix: &str) -> String {
+        match template::placeholders(&self.prompt) {
+            Ok(placeholders) if placeholders.is_empty() => format!("{}{}", self.prompt, suffix),
+            _ => self.prompt.clone(),
+        }
+    }
+}
+
+/// The output format of the free text responses of a Large Language Model.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum ResponseFormatCfg {
+    /// `Key: value` lines (e.g., `Title: ...\nSummary: ...`).
+    #[default]
+    KeyValue,
+    /// A JSON object, possibly inside a Markdown code fence.
+    Json,
+    /// A YAML mapping, possibly inside a Markdown code fence.
+    Yaml,
+    /// Elements enclosed in XML-style tags (e.g., `<title>...</title>`).
+    XmlTags,
 }
 
 // TODO: change this to /etc/liaisons/default when client is ready to run as a
@@ -69,12 +877,316 @@ impl Settings {
         
         let config = builder.build()?;
         
-        config.try_deserialize()
+        let mut settings: Self = config.try_deserialize()?;
+
+        settings.prediction.normalize_labels()?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.llm.validate()?;
+
+        let with_examples = self.prompts.examples.is_some();
+
+        self.prompts.summary.validate(template::SUMMARIZE_PLACEHOLDERS, with_examples)
+            .map_err(|e| ConfigError::Message(format!("invalid summary prompt: {}", e)))?;
+
+        if self.prompts.summary.arguments != ArgumentFieldsCfg::default() {
+            return Err(ConfigError::Message(String::from("arguments can only be set on the predict and tie_break prompts")))
+        }
+        self.prompts.predict.validate(template::PREDICT_PLACEHOLDERS, with_examples)
+            .map_err(|e| ConfigError::Message(format!("invalid predict prompt: {}", e)))?;
+
+        if let Some(ref tie_break) = self.prompts.tie_break {
+            tie_break.validate(template::PREDICT_PLACEHOLDERS, with_examples)
+                .map_err(|e| ConfigError::Message(format!("invalid tie_break prompt: {}", e)))?;
+        }
+
+        if let Some(ref reduce) = self.prompts.reduce {
+            reduce.validate(template::REDUCE_PLACEHOLDERS, false)
+                .map_err(|e| ConfigError::Message(format!("invalid reduce prompt: {}", e)))?;
+        }
+
+        if self.llm.summary_context().overflow == OverflowCfg::MapReduce && self.prompts.reduce.is_none() {
+            return Err(ConfigError::Message(String::from("the MapReduce overflow handling requires a reduce prompt")))
+        }
+
+        if let (ConsistencyCfg::TieBreak, None) = (&self.prediction.consistency, &self.prompts.tie_break) {
+            return Err(ConfigError::Message(String::from("the TieBreak consistency check requires a tie_break prompt")))
+        }
+
+        if self.concurrency.max_requests == 0 || self.concurrency.batch_size == 0 {
+            return Err(ConfigError::Message(String::from("concurrency max_requests and batch_size must be at least 1")))
+        }
+
+        Ok(())
     }
 
     pub fn merge_to_cli(& mut self, cli: &Cli) {
         if let Some(ref level) = cli.log.level {
             self.log.level = level.clone();
         }
+
+        if let Some(max_requests) = cli.concurrency.max_requests {
+            self.concurrency.max_requests = max_requests;
+        }
+
+        if let Some(ref output) = cli.metrics.output {
+            self.metrics.output = Some(output.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod model_options {
+        use super::*;
+
+        #[test]
+        fn merge_prefers_overrides() {
+            let base = ModelOptions {
+                temperature: Some(0.8),
+                seed: Some(7),
+                ..Default::default()
+            };
+            let overrides = ModelOptions {
+                temperature: Some(0.0),
+                ..Default::default()
+            };
+
+            let merged = overrides.merge(base);
+
+            assert_eq!(merged.temperature, Some(0.0));
+            assert_eq!(merged.seed, Some(7));
+            assert_eq!(merged.top_k, None);
+        }
+
+        #[test]
+        fn serializes_only_set_parameters() {
+            let options = ModelOptions {
+                temperature: Some(0.0),
+                stop: Some(vec![String::from("\n\n")]),
+                ..Default::default()
+            };
+
+            assert_eq!(
+                serde_json::to_value(&options).unwrap(),
+                serde_json::json!({ "temperature": 0.0, "stop": ["\n\n"] }),
+            );
+        }
+    }
+
+    mod llm_cfg {
+        use super::*;
+
+        fn from_yaml(yaml: &str) -> LLMCfg {
+            config::Config::builder()
+                .add_source(File::from_str(yaml, FileFormat::Yaml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap()
+        }
+
+        #[test]
+        fn deserializes_ensemble() {
+            let cfg = from_yaml(r#"
+Ensemble:
+  voting: Weighted
+  members:
+    - llm:
+        Ollama:
+          uri: http://localhost:11434
+          model: llama3
+      weight: 2.0
+    - llm:
+        OpenAICompatible:
+          uri: http://localhost:8000
+          model: mistral
+"#);
+
+            let LLMCfg::Ensemble(ref ensemble_cfg) = cfg else {
+                panic!("expected an ensemble configuration");
+            };
+
+            assert!(matches!(ensemble_cfg.voting, VotingCfg::Weighted));
+            assert_eq!(ensemble_cfg.members[0].weight, 2.0);
+            assert_eq!(ensemble_cfg.members[1].weight, 1.0);
+            assert_eq!(cfg.model_name(), "llama3,mistral");
+            assert!(cfg.validate().is_ok());
+        }
+
+        #[test]
+        fn rejects_empty_ensemble() {
+            let cfg = from_yaml("Ensemble:\n  members: []\n");
+
+            assert!(cfg.validate().is_err());
+        }
+
+        #[test]
+        fn rejects_nested_ensemble() {
+            let cfg = from_yaml(r#"
+Ensemble:
+  members:
+    - llm:
+        Ensemble:
+          members:
+            - llm:
+                Ollama:
+                  uri: http://localhost:11434
+                  model: llama3
+"#);
+
+            assert!(cfg.validate().is_err());
+        }
+    }
+
+    mod concurrency_cfg {
+        use super::*;
+
+        #[test]
+        fn defaults_missing_fields() {
+            let cfg: ConcurrencyCfg = config::Config::builder()
+                .add_source(File::from_str("max_requests: 4", FileFormat::Yaml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap();
+
+            assert_eq!(cfg.max_requests, 4);
+            assert_eq!(cfg.batch_size, 32);
+        }
+    }
+
+    mod prediction_cfg {
+        use super::*;
+
+        fn from_yaml(yaml: &str) -> Result<PredictionCfg, ConfigError> {
+            let mut cfg: PredictionCfg = config::Config::builder()
+                .add_source(File::from_str(yaml, FileFormat::Yaml))
+                .build()
+                .unwrap()
+                .try_deserialize()
+                .unwrap();
+
+            cfg.normalize_labels().map(|_| cfg)
+        }
+
+        #[test]
+        fn maps_fine_relations_onto_coarse_scheme() {
+            let cfg = from_yaml("taxonomy:\n  FineToCoarse:\n    mapping:\n      undercuts: NotRelated").unwrap();
+
+            assert_eq!(cfg.taxonomy.map(models::RelationType::Undercut), models::RelationType::NotRelated);
+            assert_eq!(cfg.taxonomy.map(models::RelationType::Rebut), models::RelationType::Attack);
+            assert_eq!(cfg.taxonomy.map(models::RelationType::PartialSupport), models::RelationType::Support);
+        }
+
+        #[test]
+        fn rejects_unknown_mapped_relation() {
+            assert!(from_yaml("taxonomy:\n  FineToCoarse:\n    mapping:\n      Refute: Attack").is_err());
+        }
+
+        #[test]
+        fn rejects_labels_of_same_relation() {
+            assert!(from_yaml("taxonomy:\n  FineToCoarse:\n    mapping:\n      Rebut: Attack\n      rebuts: NotRelated").is_err());
+        }
+
+        #[test]
+        fn keys_aliases_by_normalized_label() {
+            let cfg = from_yaml("label_aliases:\n  Not Applicable: NotRelated").unwrap();
+
+            assert_eq!(cfg.label_aliases.get("notapplicable"), Some(&models::RelationType::NotRelated));
+        }
+
+        #[test]
+        fn rejects_aliases_normalized_alike() {
+            assert!(from_yaml("label_aliases:\n  No relation: NotRelated\n  no_relation: Support").is_err());
+        }
+    }
+
+    mod prompt {
+        use super::*;
+
+        fn prompt(template: &str) -> Prompt {
+            Prompt {
+                system: None,
+                prompt: String::from(template),
+                format: ResponseFormatCfg::KeyValue,
+                arguments: ArgumentFieldsCfg::Raw,
+                endpoint: EndpointCfg::Generate,
+            }
+        }
+
+        fn argument(title: &str, summary: &str, raw: &str) -> models::Argument {
+            models::Argument::with_id(
+                1,
+                models::SummarizedInfo {
+                    title: String::from(title),
+                    summary: String::from(summary),
+                    quote: None,
+                    structure: None,
+                },
+                String::from(raw),
+                None,
+            )
+        }
+
+        #[test]
+        fn renders_predict_placeholders() {
+            let rendered = prompt("A: {{arg_a.title}} - {{arg_a.summary}}\nB: {{arg_b.title}}")
+                .render_predict(
+                    &argument("Taxes", "Taxes should be lower.", "Taxes are too high!"),
+                    &argument("Spending", "Spending should be cut.", "Cut spending!"),
+                    "",
+                );
+
+            assert_eq!(rendered, "A: Taxes - Taxes should be lower.\nB: Spending");
+        }
+
+        #[test]
+        fn appends_content_without_placeholders() {
+            let rendered = prompt("Summarize the argument.")
+                .render_summarize(&models::Post {
+                    content: String::from("Taxes are too high."),
+                    author: None,
+                    thread_context: None,
+                }, "");
+
+            assert_eq!(rendered, "Summarize the argument.\n\nArg:Taxes are too high.\n");
+        }
+
+        #[test]
+        fn appends_selected_fields_without_placeholders() {
+            let mut prompt = prompt("Predict the relation.");
+            prompt.arguments = ArgumentFieldsCfg::TitleSummary;
+
+            let rendered = prompt.render_predict(
+                &argument("Taxes", "Taxes should be lower.", "Taxes are too high!"),
+                &argument("Spending", "Spending should be cut.", "Cut spending!"),
+                "",
+            );
+
+            assert_eq!(
+                rendered,
+                "Predict the relation.\n\nArg1:Taxes\nTaxes should be lower.\nArg2:Spending\nSpending should be cut.\n",
+            );
+        }
+
+        #[test]
+        fn requires_examples_placeholder_with_examples() {
+            let templated = prompt("A: {{arg_a}}\nB: {{arg_b}}");
+
+            assert!(templated.validate(template::PREDICT_PLACEHOLDERS, false).is_ok());
+            assert!(matches!(
+                templated.validate(template::PREDICT_PLACEHOLDERS, true),
+                Err(template::Error::MissingPlaceholder("examples")),
+            ));
+            assert!(prompt("{{examples}}\nA: {{arg_a}}").validate(template::PREDICT_PLACEHOLDERS, true).is_ok());
+            assert!(prompt("Predict the relation.").validate(template::PREDICT_PLACEHOLDERS, true).is_ok());
+        }
     }
 }
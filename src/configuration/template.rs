@@ -0,0 +1,128 @@
+use thiserror::Error as ThisError;
+
+/// The placeholders available in the summarization prompts.
+pub const SUMMARIZE_PLACEHOLDERS: &[&str] = &["raw", "author", "thread_context", "examples"];
+
+/// The placeholders available in the prompts merging the partial summaries
+/// of a post.
+pub const REDUCE_PLACEHOLDERS: &[&str] = &["summaries", "author", "thread_context"];
+
+/// The placeholders available in the relation prediction prompts.
+pub const PREDICT_PLACEHOLDERS: &[&str] = &[
+    "arg_a", "arg_b",
+    "arg_a.raw", "arg_a.title", "arg_a.summary",
+    "arg_b.raw", "arg_b.title", "arg_b.summary",
+    "examples",
+];
+
+#[derive(Debug, ThisError, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("unknown placeholder \"{{{{{0}}}}}\", expected one of {1:?}")]
+    UnknownPlaceholder(String, &'static [&'static str]),
+
+    #[error("unclosed placeholder at byte {0}")]
+    UnclosedPlaceholder(usize),
+
+    #[error("missing placeholder \"{{{{{0}}}}}\"")]
+    MissingPlaceholder(&'static str),
+}
+
+/// Return the names of the `{{name}}` placeholders of a template, in order of
+/// appearance.
+pub fn placeholders(template: &str) -> Result<Vec<&str>, Error> {
+    let mut names = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            return Err(Error::UnclosedPlaceholder(template.len() - rest.len() + start))
+        };
+
+        names.push(rest[start + 2..start + end].trim());
+        rest = &rest[start + end + 2..];
+    }
+
+    Ok(names)
+}
+
+/// Check that every placeholder of a template is one of `allowed`.
+pub fn validate(template: &str, allowed: &'static [&'static str]) -> Result<(), Error> {
+    for name in placeholders(template)? {
+        if !allowed.contains(&name) {
+            return Err(Error::UnknownPlaceholder(String::from(name), allowed))
+        }
+    }
+
+    Ok(())
+}
+
+/// Render a template, replacing each of its placeholders with its value in
+/// `values`. Placeholders without a value are replaced by an empty string.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break
+        };
+
+        let name = rest[start + 2..start + end].trim();
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(values.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+            .unwrap_or_default());
+        rest = &rest[start + end + 2..];
+    }
+
+    rendered.push_str(rest);
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod validate {
+        use super::*;
+
+        #[test]
+        fn accepts_known_placeholders() {
+            assert!(validate("Compare {{ arg_a.summary }} with {{arg_b.title}}.", PREDICT_PLACEHOLDERS).is_ok());
+        }
+
+        #[test]
+        fn rejects_unknown_placeholders() {
+            assert_eq!(
+                validate("Summarize {{content}}.", SUMMARIZE_PLACEHOLDERS),
+                Err(Error::UnknownPlaceholder(String::from("content"), SUMMARIZE_PLACEHOLDERS)),
+            );
+        }
+
+        #[test]
+        fn rejects_unclosed_placeholders() {
+            assert_eq!(
+                validate("Summarize {{raw}} from {{author", SUMMARIZE_PLACEHOLDERS),
+                Err(Error::UnclosedPlaceholder(23)),
+            );
+        }
+    }
+
+    mod render {
+        use super::*;
+
+        #[test]
+        fn replaces_placeholders() {
+            let rendered = render(
+                "Post by {{author}}:\n{{ raw }}\n{{thread_context}}",
+                &[("raw", "Taxes are too high."), ("author", "alice")],
+            );
+
+            assert_eq!(rendered, "Post by alice:\nTaxes are too high.\n");
+        }
+    }
+}
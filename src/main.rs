@@ -5,7 +5,6 @@ mod models;
 
 use configuration::*;
 use subcommands::{predict, summarize};
-use tokio;
 
 #[tokio::main]
 async fn main() {
@@ -21,16 +20,30 @@ async fn main() {
             setup_logger(&settings.log);
 
             match &cli.command {
-                Some(Commands::SummarizeArguments { file, system, prompt }) => {
+                Some(Commands::SummarizeArguments { file, system, prompt, options }) => {
                     settings.prompts.summary = Prompt {
                         system: system.clone().or(settings.prompts.summary.system),
                         prompt: prompt.clone().unwrap_or(settings.prompts.summary.prompt),
+                        format: settings.prompts.summary.format,
+                        arguments: settings.prompts.summary.arguments,
+                        endpoint: settings.prompts.summary.endpoint,
                     };
+                    settings.llm.override_summarize_options(options.into());
+
+                    if let Err(ref e) = settings.prompts.summary.validate(template::SUMMARIZE_PLACEHOLDERS, settings.prompts.examples.is_some()) {
+                        log::error!("invalid summary prompt: {}", e);
+
+                        return
+                    }
 
                     let cfg = summarize::SummarizeArgumentCfg {
                         llm_cfg: settings.llm,
                         repo_cfg: settings.repository,
                         prompt: settings.prompts.summary,
+                        reduce_prompt: settings.prompts.reduce,
+                        examples: settings.prompts.examples,
+                        concurrency: settings.concurrency,
+                        metrics: settings.metrics,
                         file_path: file.to_path_buf(),
                     };
 
@@ -39,16 +52,31 @@ async fn main() {
                             log::error!("arguments summarize failed: {}", e);
                     }
                 },
-                Some(Commands::PredictRelations { args_id, system, prompt }) => {
+                Some(Commands::PredictRelations { args_id, system, prompt, options }) => {
                     settings.prompts.predict = Prompt {
                         system: system.clone().or(settings.prompts.predict.system),
                         prompt: prompt.clone().unwrap_or(settings.prompts.predict.prompt),
+                        format: settings.prompts.predict.format,
+                        arguments: settings.prompts.predict.arguments,
+                        endpoint: settings.prompts.predict.endpoint,
                     };
+                    settings.llm.override_predict_options(options.into());
+
+                    if let Err(ref e) = settings.prompts.predict.validate(template::PREDICT_PLACEHOLDERS, settings.prompts.examples.is_some()) {
+                        log::error!("invalid predict prompt: {}", e);
+
+                        return
+                    }
 
                     let cfg = predict::PredictRelationCfg {
                         llm_cfg: settings.llm,
                         repo_cfg: settings.repository,
                         prompt: settings.prompts.predict,
+                        tie_break_prompt: settings.prompts.tie_break,
+                        examples: settings.prompts.examples,
+                        prediction: settings.prediction,
+                        concurrency: settings.concurrency,
+                        metrics: settings.metrics,
                         args_id: args_id.clone(),
                     };
 
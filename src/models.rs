@@ -1,5 +1,9 @@
 pub mod argument;
+pub mod example;
+pub mod post;
 pub mod relation;
 
 pub use argument::*;
+pub use example::*;
+pub use post::*;
 pub use relation::*;
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// Represents an argument.
@@ -5,32 +6,58 @@ use serde::{Deserialize, Serialize};
 pub struct Argument {
     pub id: Option<u32>,
     pub summarized_info: SummarizedInfo,
-    /// The user-generated web content from which the argument was extracted
-    /// as a raw string.
+    /// The passage of the user-generated web content stating the argument, or
+    /// the whole content if the passage could not be located.
     pub raw: String,
+    /// The character offset of `raw` in the content it was extracted from,
+    /// unknown if the passage could not be located.
+    #[serde(default)]
+    pub offset: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct SummarizedInfo {
     pub title: String,
     /// The extracted argument summarized as a string.
     pub summary: String,
+    /// The passage of the content stating the argument, quoted verbatim, if
+    /// the model gave one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+    /// The breakdown of how the argument is built, if the model gave one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structure: Option<ArgumentStructure>,
+}
+
+/// Represents the claim/premise structure of an argument.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ArgumentStructure {
+    /// The main claim defended by the argument.
+    pub claim: String,
+    /// The premises given in support of the claim.
+    #[serde(default)]
+    pub premises: Vec<String>,
+    /// The conclusion or warrant linking the premises to the claim, if stated.
+    #[serde(default)]
+    pub conclusion: Option<String>,
 }
 
 impl Argument {
-    pub fn new(summarized_info: SummarizedInfo, raw: String) -> Self {
+    pub fn new(summarized_info: SummarizedInfo, raw: String, offset: Option<u32>) -> Self {
         Self {
             id: None,
-            summarized_info: summarized_info,
-            raw: raw,
+            summarized_info,
+            raw,
+            offset,
         }
     }
 
-    pub fn with_id(id: u32, summarized_info: SummarizedInfo, raw: String) -> Self {
+    pub fn with_id(id: u32, summarized_info: SummarizedInfo, raw: String, offset: Option<u32>) -> Self {
         Self {
             id: Some(id),
-            summarized_info: summarized_info,
-            raw: raw,
+            summarized_info,
+            raw,
+            offset,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Argument, ArgumentStructure, RelationType, SummarizedInfo};
+
+/// A library of labeled examples, injected in the prompts as few-shot
+/// demonstrations.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Examples {
+    #[serde(default)]
+    pub summarize: Vec<SummarizeExample>,
+    #[serde(default)]
+    pub predict: Vec<PredictExample>,
+}
+
+/// A post along with the expected title and summary of its argument.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SummarizeExample {
+    pub content: String,
+    pub title: String,
+    pub summary: String,
+    /// The passage of the post stating the argument, if the example quotes
+    /// one.
+    #[serde(default)]
+    pub quote: Option<String>,
+    /// The expected structure of the argument, if the example shows one.
+    #[serde(default)]
+    pub structure: Option<ArgumentStructure>,
+}
+
+/// A pair of posts along with the expected relation of the first one toward
+/// the second one.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PredictExample {
+    pub arg_a: String,
+    pub arg_b: String,
+    /// The summarized argument of `arg_a`, required by the argument
+    /// representations other than the raw content.
+    #[serde(default)]
+    pub summary_a: Option<SummarizedInfo>,
+    /// The summarized argument of `arg_b`.
+    #[serde(default)]
+    pub summary_b: Option<SummarizedInfo>,
+    pub relation: RelationType,
+    #[serde(default)]
+    pub explanation: String,
+}
+
+impl PredictExample {
+    /// Return whether both arguments of the example are summarized.
+    pub fn is_summarized(&self) -> bool {
+        self.summary_a.is_some() && self.summary_b.is_some()
+    }
+
+    /// Return both arguments of the example, with an empty summary if they
+    /// are not summarized.
+    pub fn arguments(&self) -> (Argument, Argument) {
+        let argument = |raw: &str, summary: &Option<SummarizedInfo>| Argument::new(
+            summary.clone().unwrap_or_else(|| SummarizedInfo {
+                title: String::new(),
+                summary: String::new(),
+                quote: None,
+                structure: None,
+            }),
+            String::from(raw),
+            None,
+        );
+
+        (argument(&self.arg_a, &self.summary_a), argument(&self.arg_b, &self.summary_b))
+    }
+}
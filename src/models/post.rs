@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Argument;
+
+/// Represents a user-generated web content (e.g., Twitter Post) to extract
+/// arguments from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Post {
+    /// The content of the post as a raw string.
+    pub content: String,
+    /// The name of the author of the post, if known.
+    #[serde(default)]
+    pub author: Option<String>,
+    /// The content of the posts the post replies to, if any.
+    #[serde(default)]
+    pub thread_context: Option<String>,
+}
+
+impl Post {
+    /// Return the character offset of a passage quoted verbatim from the
+    /// content of the post, if it appears in it.
+    pub fn offset_of(&self, passage: &str) -> Option<u32> {
+        let start = self.content.find(passage)?;
+
+        u32::try_from(self.content[..start].chars().count()).ok()
+    }
+}
+
+/// Represents a post along with the arguments extracted from it, in order of
+/// appearance.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExtractedPost {
+    pub post: Post,
+    pub arguments: Vec<Argument>,
+}
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -7,22 +8,75 @@ pub struct Relation {
     pub relation_type: RelationType,
     pub confidence: f32,
     pub explanation: String,
+    /// The individual predictions the relation was reduced from, if several
+    /// were made.
+    #[serde(default)]
+    pub votes: Vec<Vote>,
+    /// Whether the relation contradicts the relation predicted in the opposite
+    /// direction.
+    #[serde(default)]
+    pub contradicted: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// A single prediction of a relation, kept for audit when several predictions
+/// are reduced to one relation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Vote {
+    /// The name of the model which made the prediction.
+    #[serde(default)]
+    pub model: String,
+    pub relation_type: RelationType,
+    pub confidence: f32,
+    pub explanation: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
 pub enum RelationType {
     Support,
     Attack,
     NotRelated,
+    /// An attack on the claim of the other argument.
+    Rebut,
+    /// An attack on the inference from the premises of the other argument to
+    /// its claim.
+    Undercut,
+    /// An attack on a premise of the other argument.
+    Undermine,
+    /// A support of only part of the other argument.
+    PartialSupport,
 }
 
 impl RelationType {
-    pub fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "support" => RelationType::Support,
-            "attack" => RelationType::Attack,
-            "not related" => RelationType::NotRelated,
-            _ => RelationType::NotRelated,
+    /// The relations of the coarse three-way scheme.
+    pub const COARSE: [RelationType; 3] = [
+        RelationType::Support,
+        RelationType::Attack,
+        RelationType::NotRelated,
+    ];
+
+    /// The finer-grained argumentation relations.
+    pub const FINE: [RelationType; 6] = [
+        RelationType::Support,
+        RelationType::PartialSupport,
+        RelationType::Rebut,
+        RelationType::Undercut,
+        RelationType::Undermine,
+        RelationType::NotRelated,
+    ];
+
+    /// Read a relation label, tolerating the usual variants of the labels
+    /// (case, spacing, Markdown emphasis, quotes, inflections such as
+    /// "Supports"). Return `None` for an unknown label rather than guessing.
+    pub fn parse(label: &str) -> Option<Self> {
+        match normalize_label(label).as_str() {
+            "support" | "supports" | "supported" | "supporting" => Some(RelationType::Support),
+            "attack" | "attacks" | "attacked" | "attacking" => Some(RelationType::Attack),
+            "notrelated" | "unrelated" | "norelation" => Some(RelationType::NotRelated),
+            "rebut" | "rebuts" | "rebuttal" => Some(RelationType::Rebut),
+            "undercut" | "undercuts" => Some(RelationType::Undercut),
+            "undermine" | "undermines" => Some(RelationType::Undermine),
+            "partialsupport" | "partiallysupports" | "partiallysupport" => Some(RelationType::PartialSupport),
+            _ => None,
         }
     }
 
@@ -31,6 +85,63 @@ impl RelationType {
             RelationType::Support => "Support",
             RelationType::Attack => "Attack",
             RelationType::NotRelated => "NotRelated",
+            RelationType::Rebut => "Rebut",
+            RelationType::Undercut => "Undercut",
+            RelationType::Undermine => "Undermine",
+            RelationType::PartialSupport => "PartialSupport",
+        }
+    }
+
+    /// Return the relation of the coarse three-way scheme the relation
+    /// belongs to by default.
+    pub fn coarse(&self) -> RelationType {
+        match self {
+            RelationType::Support | RelationType::PartialSupport => RelationType::Support,
+            RelationType::Attack | RelationType::Rebut | RelationType::Undercut | RelationType::Undermine => RelationType::Attack,
+            RelationType::NotRelated => RelationType::NotRelated,
+        }
+    }
+}
+
+/// Normalize a relation label for comparison: lowercased, without the
+/// surrounding Markdown emphasis, quotes and punctuation, and without any
+/// whitespace, underscore or hyphen (e.g., "**Not related.**" gives
+/// "notrelated").
+pub fn normalize_label(label: &str) -> String {
+    label.trim()
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse {
+        use super::*;
+
+        #[test]
+        fn reads_fine_grained_relations() {
+            assert_eq!(RelationType::parse("Undercut"), Some(RelationType::Undercut));
+            assert_eq!(RelationType::parse("partial support"), Some(RelationType::PartialSupport));
+            assert_eq!(RelationType::parse(RelationType::PartialSupport.to_str()), Some(RelationType::PartialSupport));
+        }
+
+        #[test]
+        fn normalizes_common_variants() {
+            assert_eq!(RelationType::parse("**Supports**"), Some(RelationType::Support));
+            assert_eq!(RelationType::parse("`not_related`."), Some(RelationType::NotRelated));
+            assert_eq!(RelationType::parse("\"Attack\""), Some(RelationType::Attack));
+        }
+
+        #[test]
+        fn rejects_unknown_labels() {
+            assert_eq!(RelationType::parse("Neutral"), None);
+            assert_eq!(RelationType::parse("Suport"), None);
+            assert_eq!(RelationType::parse(""), None);
         }
     }
 }
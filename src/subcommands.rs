@@ -1,2 +1,3 @@
+mod metrics;
 pub mod predict;
 pub mod summarize;
@@ -0,0 +1,24 @@
+use tokio::fs;
+
+use crate::clients::llm;
+use crate::configuration::settings::MetricsCfg;
+
+/// Print the summary of the inference usage of a run, and write it as JSON
+/// to the configured output if any, logging the failure of the write rather
+/// than failing the run.
+pub async fn report(stats: llm::InferenceStats, cfg: &MetricsCfg) {
+    let summary = stats.summary();
+
+    println!("{}", summary);
+
+    if let Some(ref output) = cfg.output {
+        let written = match serde_json::to_string_pretty(&summary) {
+            Ok(json) => fs::write(output, json).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        if let Err(e) = written {
+            log::error!("failed to write inference metrics to {}: {}", output.display(), e);
+        }
+    }
+}
@@ -1,12 +1,18 @@
+mod pre_filter;
+
+use futures::stream::{self, StreamExt};
 use thiserror::Error as ThisError;
 use crate::clients::llm::ClientTrait;
-use crate::configuration::settings::{LLMCfg, RepositoryCfg, Prompt};
+use crate::configuration::settings::{LLMCfg, RepositoryCfg, Prompt, PredictionCfg, PredictionStrategyCfg, ModelOptions, VotingCfg, ConsistencyCfg, ConcurrencyCfg, ExamplesCfg, MetricsCfg, ArgumentFieldsCfg};
 use crate::clients::{repository, llm};
 use crate::clients::repository::RepositoryTrait;
 use crate::models;
+use crate::clients::llm::few_shot::{self, FewShot};
+use crate::subcommands::metrics;
 
 
 #[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("io error: {0}")]
     IOError(#[from] std::io::Error),
@@ -19,12 +25,23 @@ pub enum Error {
 
     #[error("llm client error: {0}")]
     LLMError(#[from] llm::Error),
+
+    #[error("few-shot examples error: {0}")]
+    FewShotError(#[from] few_shot::Error),
+
+    #[error("few-shot example without summaries, required by the {0:?} argument representation")]
+    UnsummarizedExample(ArgumentFieldsCfg),
 }
 
 pub struct PredictRelationCfg {
     pub llm_cfg: LLMCfg,
     pub repo_cfg: RepositoryCfg,
     pub prompt: Prompt,
+    pub tie_break_prompt: Option<Prompt>,
+    pub examples: Option<ExamplesCfg>,
+    pub prediction: PredictionCfg,
+    pub concurrency: ConcurrencyCfg,
+    pub metrics: MetricsCfg,
     pub args_id: Vec<u32>,
 }
 
@@ -34,29 +51,659 @@ pub async fn predict_relations(cfg: PredictRelationCfg) -> Result<(), Error> {
         .await?;
     let mut args = Vec::<models::Argument>::with_capacity(cfg.args_id.len());
 
-    for arg_id in cfg.args_id {
+    for &arg_id in cfg.args_id.iter() {
         args.push(repo_client.retrieve_argument(arg_id).await?);
     }
 
-    let llm_client = llm::Client::new(&cfg.llm_cfg);
+    let mut llm_cfg = cfg.llm_cfg.clone();
 
-    for arg_a in args.iter() {
-        for arg_b in args.iter() {
-            if arg_a.id == arg_b.id {
-                continue
-            } else {
-                let relation = llm_client.predict(&cfg.prompt, arg_a, arg_b).await?;
+    if let PredictionStrategyCfg::SelfConsistency { temperature, .. } = cfg.prediction.strategy {
+        llm_cfg.override_predict_options(ModelOptions {
+            temperature: Some(temperature),
+            ..Default::default()
+        });
+    }
 
-                repo_client.add_relation(relation).await?;
-            }
+    let examples = few_shot::load_examples(cfg.examples.as_ref()).await?;
+
+    for prompt in std::iter::once(&cfg.prompt).chain(cfg.tie_break_prompt.as_ref()) {
+        if !matches!(prompt.arguments, ArgumentFieldsCfg::Raw) && !examples.predict.iter().all(models::PredictExample::is_summarized) {
+            return Err(Error::UnsummarizedExample(prompt.arguments.clone()))
         }
     }
 
+    let predictor = Predictor {
+        members: Member::from_cfg(&llm_cfg),
+        few_shot: FewShot::new(examples.predict, cfg.examples.as_ref(), &llm_cfg).await?,
+        voting: match llm_cfg {
+            LLMCfg::Ensemble(ref ensemble_cfg) => ensemble_cfg.voting.clone(),
+            _ => VotingCfg::Majority,
+        },
+        prediction: cfg.prediction,
+        prompt: cfg.prompt,
+        tie_break_prompt: cfg.tie_break_prompt,
+    };
+
+    // The arguments are embedded by the first member, their requests counting
+    // toward its usage.
+    let mut pairs = pre_filter::candidate_pairs(
+        &predictor.members[0].client,
+        &args,
+        &predictor.prediction.pre_filter,
+        cfg.concurrency.max_requests,
+    ).await?;
+
+    if predictor.prediction.skip_stored {
+        let stored = repo_client.retrieve_relations(&cfg.args_id).await?;
+        let count = pairs.len();
+
+        pairs = unstored_pairs(pairs, &args, &stored);
+
+        log::info!("skipping {} pairs of arguments with stored relations", count - pairs.len());
+    }
+
+    let predictor = &predictor;
+    let args = &args;
+    let repairs = &llm::RepairCounter::default();
+
+    // Pairs are predicted concurrently but their relations are collected in
+    // pair order, a failed pair being logged without aborting the others.
+    let mut predictions = stream::iter(pairs.into_iter().filter(|&(i, j)| args[i].id != args[j].id))
+        .map(|(i, j)| async move {
+            (i, j, repairs.track(predictor.predict_pair(&args[i], &args[j])).await)
+        })
+        .buffered(cfg.concurrency.max_requests);
+
+    let mut batch = Vec::with_capacity(cfg.concurrency.batch_size);
+
+    while let Some((i, j, result)) = predictions.next().await {
+        match result {
+            Ok(relations) => batch.extend(relations),
+            Err(e) => log::error!(
+                "failed to predict the relations between arguments {:?} and {:?}: {}",
+                args[i].id, args[j].id, e,
+            ),
+        }
+
+        if batch.len() >= cfg.concurrency.batch_size {
+            add_relations(&mut repo_client, std::mem::take(&mut batch)).await;
+        }
+    }
+
+    add_relations(&mut repo_client, batch).await;
+
+    let repairs = repairs.stats();
+
+    log::info!("{} pairs of arguments needed repair, {} of them were repaired", repairs.needed, repairs.repaired);
+
+    let stats: llm::InferenceStats = predictor.members.iter()
+        .map(|member| member.client.inference_stats())
+        .sum();
+
+    metrics::report(stats, &cfg.metrics).await;
+
     Ok(())
 }
 
+/// Store a batch of predicted relations, logging the failure of the batch
+/// rather than aborting the remaining predictions.
+async fn add_relations(repo_client: &mut repository::Repository, relations: Vec<models::Relation>) {
+    if relations.is_empty() {
+        return
+    }
+
+    let count = relations.len();
+
+    if let Err(ref e) = repo_client.add_relations(relations).await {
+        log::error!("failed to create {} relations in Neo4j database: {}", count, e);
+    };
+}
+
+/// A model taking part in the relations prediction.
+struct Member {
+    client: llm::Client,
+    /// The name of the model, recorded in its votes.
+    model: String,
+    /// The weight of the model votes.
+    weight: f32,
+}
+
+impl Member {
+    /// Create the members of the prediction, one per model of an ensemble or
+    /// a single one otherwise.
+    fn from_cfg(llm_cfg: &LLMCfg) -> Vec<Self> {
+        match llm_cfg {
+            LLMCfg::Ensemble(ensemble_cfg) => ensemble_cfg.members.iter()
+                .map(|member| Member {
+                    client: llm::Client::new(&member.llm),
+                    model: member.llm.model_name(),
+                    weight: member.weight,
+                })
+                .collect(),
+            llm_cfg => vec![Member {
+                client: llm::Client::new(llm_cfg),
+                model: llm_cfg.model_name(),
+                weight: 1.0,
+            }],
+        }
+    }
+}
+
+/// A relation predicted by a model, to be combined with others by vote.
+struct Ballot {
+    relation: models::Relation,
+    model: String,
+    weight: f32,
+}
+
+/// The relation voted by the members, along with the turns of the
+/// conversation in which each member predicted it, in order of the members.
+struct Voted {
+    relation: Option<models::Relation>,
+    turns: Vec<Vec<llm::chat::Message>>,
+}
+
+/// Predict the relations of pairs of arguments with the configured members,
+/// strategy and consistency check.
+struct Predictor {
+    members: Vec<Member>,
+    few_shot: FewShot<models::PredictExample>,
+    voting: VotingCfg,
+    prediction: PredictionCfg,
+    prompt: Prompt,
+    tie_break_prompt: Option<Prompt>,
+}
+
+impl Predictor {
+    /// Predict the relations of `arg_a` toward `arg_b` and of `arg_b` toward
+    /// `arg_a`, checking that they do not contradict each other.
+    async fn predict_pair(&self, arg_a: &models::Argument, arg_b: &models::Argument) -> Result<Vec<models::Relation>, Error> {
+        let examples = self.few_shot.select(&format!("{}\n{}", arg_a.raw, arg_b.raw)).await?;
+
+        let forward = self.predict_relation(&self.prompt, arg_a, arg_b, &examples, &[]).await?;
+
+        let screened_out = forward.relation.as_ref()
+            .is_some_and(|relation| relation.relation_type == models::RelationType::NotRelated);
+
+        if self.prediction.symmetric_screening && screened_out {
+            log::debug!(
+                "argument {:?} is not related to argument {:?}, skipping the reverse prediction",
+                arg_a.id, arg_b.id,
+            );
+
+            return Ok(forward.relation.into_iter().collect())
+        }
+
+        let backward = self.predict_relation(&self.prompt, arg_b, arg_a, &examples, &[]).await?;
+
+        // The conversations of each member in both directions, continued by
+        // the tie-break prompt.
+        let histories: Vec<Vec<llm::chat::Message>> = forward.turns.into_iter()
+            .zip(backward.turns)
+            .map(|(forward, backward)| forward.into_iter().chain(backward).collect())
+            .collect();
+
+        match (forward.relation, backward.relation) {
+            (Some(forward), Some(backward)) if contradicts(&forward, &backward) =>
+                self.resolve_contradiction(forward, backward, arg_a, arg_b, &examples, &histories).await,
+            (forward, backward) => Ok(forward.into_iter().chain(backward).collect()),
+        }
+    }
+
+    /// Handle a pair of contradictory relations with the configured
+    /// consistency check.
+    async fn resolve_contradiction(&self, mut forward: models::Relation, mut backward: models::Relation, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], histories: &[Vec<llm::chat::Message>]) -> Result<Vec<models::Relation>, Error> {
+        log::info!(
+            "relations between arguments {:?} and {:?} contradict each other",
+            arg_a.id, arg_b.id,
+        );
+
+        match self.prediction.consistency {
+            ConsistencyCfg::None => (),
+            ConsistencyCfg::Flag => {
+                forward.contradicted = true;
+                backward.contradicted = true;
+            },
+            ConsistencyCfg::Penalize { factor } => {
+                forward.confidence *= factor;
+                forward.contradicted = true;
+                backward.confidence *= factor;
+                backward.contradicted = true;
+            },
+            ConsistencyCfg::TieBreak => {
+                // The tie-break prompt presence is checked at configuration
+                // loading.
+                let prompt = self.tie_break_prompt.as_ref().unwrap();
+
+                // The tie-break prompt continues the conversation in which each
+                // member predicted the contradictory relations.
+                let tie_break_forward = self.predict_relation(prompt, arg_a, arg_b, examples, histories).await?.relation;
+                let tie_break_backward = self.predict_relation(prompt, arg_b, arg_a, examples, histories).await?.relation;
+
+                let mut relations: Vec<models::Relation> = tie_break_forward.into_iter()
+                    .chain(tie_break_backward)
+                    .collect();
+
+                if let [forward, backward] = relations.as_mut_slice() {
+                    if contradicts(forward, backward) {
+                        forward.contradicted = true;
+                        backward.contradicted = true;
+                    }
+                }
+
+                return Ok(relations)
+            },
+        }
+
+        Ok(vec![forward, backward])
+    }
+
+    /// Predict the relation of `arg_a` toward `arg_b` with every member,
+    /// combining their predictions by vote if there are several, the prompt
+    /// of each member continuing its conversation of `histories`, if any.
+    /// The voted relation is `None` if the voting method discarded it.
+    async fn predict_relation(&self, prompt: &Prompt, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], histories: &[Vec<llm::chat::Message>]) -> Result<Voted, Error> {
+        let mut ballots = Vec::with_capacity(self.members.len());
+        let mut turns = Vec::with_capacity(self.members.len());
+
+        for (i, member) in self.members.iter().enumerate() {
+            let history = histories.get(i).map_or(&[][..], Vec::as_slice);
+            let prediction = self.predict_member_relation(member, prompt, arg_a, arg_b, examples, history).await?;
+
+            ballots.push(Ballot {
+                relation: prediction.relation,
+                model: member.model.clone(),
+                weight: member.weight,
+            });
+            turns.push(prediction.turns);
+        }
+
+        if ballots.len() == 1 {
+            return Ok(Voted {
+                relation: ballots.pop().map(|ballot| ballot.relation),
+                turns,
+            })
+        }
+
+        let relation = vote(ballots, &self.voting);
+
+        if relation.is_none() {
+            log::info!(
+                "models disagreed on the relation of argument {:?} toward argument {:?}, discarding it",
+                arg_a.id, arg_b.id,
+            );
+        }
+
+        Ok(Voted { relation, turns })
+    }
+
+    /// Predict the relation of `arg_a` toward `arg_b` with a single member and
+    /// the configured prediction strategy.
+    async fn predict_member_relation(&self, member: &Member, prompt: &Prompt, arg_a: &models::Argument, arg_b: &models::Argument, examples: &[models::PredictExample], history: &[llm::chat::Message]) -> Result<llm::chat::Prediction, Error> {
+        match self.prediction.strategy {
+            PredictionStrategyCfg::SelfConsistency { samples, .. } => {
+                let mut predictions = Vec::with_capacity(samples.max(1));
+                let mut failure = None;
+
+                // A failed sample is left out of the vote, the relation being
+                // elected from the successful ones.
+                for sample in 1..=samples.max(1) {
+                    match member.client.predict(prompt, arg_a, arg_b, examples, &self.prediction, history).await {
+                        Ok(prediction) => predictions.push(prediction),
+                        Err(e) => {
+                            log::warn!(
+                                "sample {} of the relation of argument {:?} toward argument {:?} by {} failed: {}",
+                                sample, arg_a.id, arg_b.id, member.model, e,
+                            );
+                            failure = Some(e);
+                        },
+                    }
+                }
+
+                if let Some(e) = failure.filter(|_| predictions.is_empty()) {
+                    return Err(Error::from(e))
+                }
+
+                let ballots = predictions.iter()
+                    .map(|prediction| Ballot {
+                        relation: prediction.relation.clone(),
+                        model: member.model.clone(),
+                        weight: 1.0,
+                    })
+                    .collect();
+
+                // A majority vote always elects a relation, with the share of
+                // the successful samples agreeing with it as confidence.
+                let relation = vote(ballots, &VotingCfg::Majority).unwrap();
+
+                // The conversation kept is the one of a sample electing the
+                // relation.
+                let turns = predictions.into_iter()
+                    .find(|prediction| prediction.relation.relation_type == relation.relation_type)
+                    .map(|prediction| prediction.turns)
+                    .unwrap_or_default();
+
+                Ok(llm::chat::Prediction { relation, turns })
+            },
+            PredictionStrategyCfg::Greedy => Ok(member.client.predict(prompt, arg_a, arg_b, examples, &self.prediction, history).await?),
+        }
+    }
+}
+
+/// Keep the pairs of arguments with no relation stored between them, in
+/// either direction.
+fn unstored_pairs(pairs: Vec<(usize, usize)>, args: &[models::Argument], stored: &[models::Relation]) -> Vec<(usize, usize)> {
+    pairs.into_iter()
+        .filter(|&(i, j)| !stored.iter().any(|relation| {
+            let ids = (Some(relation.arg_a_id), Some(relation.arg_b_id));
+
+            ids == (args[i].id, args[j].id) || ids == (args[j].id, args[i].id)
+        }))
+        .collect()
+}
+
+/// Whether two relations of opposite directions between the same arguments
+/// contradict each other, one supporting the other argument while the other
+/// attacks it, whatever the finer-grained kind of support or attack.
+fn contradicts(forward: &models::Relation, backward: &models::Relation) -> bool {
+    matches!(
+        (forward.relation_type.coarse(), backward.relation_type.coarse()),
+        (models::RelationType::Support, models::RelationType::Attack)
+            | (models::RelationType::Attack, models::RelationType::Support)
+    )
+}
+
+/// Combine several predictions of the same relation into one with the given
+/// voting method. The elected relation has the share of votes it received as
+/// confidence, and keeps every prediction it was elected from as votes. Ties
+/// are broken in favor of the first predicted relation.
+///
+/// Returns `None` if the `Unanimous` voting method is used and the predictions
+/// disagree.
+fn vote(ballots: Vec<Ballot>, voting: &VotingCfg) -> Option<models::Relation> {
+    let mut tallies: Vec<(&models::RelationType, f32)> = Vec::new();
+
+    for ballot in ballots.iter() {
+        let weight = match voting {
+            VotingCfg::Weighted => ballot.weight * ballot.relation.confidence,
+            VotingCfg::Majority | VotingCfg::Unanimous => 1.0,
+        };
+
+        match tallies.iter_mut().find(|(relation_type, _)| **relation_type == ballot.relation.relation_type) {
+            Some((_, tally)) => *tally += weight,
+            None => tallies.push((&ballot.relation.relation_type, weight)),
+        }
+    }
+
+    if let VotingCfg::Unanimous = voting {
+        if tallies.len() > 1 {
+            return None
+        }
+    }
+
+    let total: f32 = tallies.iter().map(|(_, tally)| tally).sum();
+    let (elected, tally) = tallies.iter()
+        .fold(tallies[0], |best, current| if current.1 > best.1 { *current } else { best });
+
+    let mut relation = ballots.iter()
+        .find(|ballot| ballot.relation.relation_type == *elected)
+        .map(|ballot| ballot.relation.clone())?;
+
+    relation.confidence = if total > 0.0 { tally / total } else { 0.0 };
+    relation.votes = ballots.iter()
+        .flat_map(|ballot| {
+            if ballot.relation.votes.is_empty() {
+                vec![models::Vote {
+                    model: ballot.model.clone(),
+                    relation_type: ballot.relation.relation_type.clone(),
+                    confidence: ballot.relation.confidence,
+                    explanation: ballot.relation.explanation.clone(),
+                }]
+            } else {
+                ballot.relation.votes.clone()
+            }
+        })
+        .collect();
+
+    Some(relation)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     mod predict_relations {
     }
+
+    mod predict_member_relation {
+        use super::*;
+        use crate::configuration::settings;
+
+        async fn predictor(uri: String, samples: usize) -> Predictor {
+            let llm_cfg = LLMCfg::Ollama(settings::OllamaCfg {
+                uri,
+                model: String::from("test-model"),
+                embedding_model: None,
+                structured_outputs: false,
+                options: ModelOptions::default(),
+                summarize_options: ModelOptions::default(),
+                predict_options: ModelOptions::default(),
+                retry: settings::RetryCfg::default(),
+                context: settings::ContextCfg::default(),
+            });
+
+            Predictor {
+                members: Member::from_cfg(&llm_cfg),
+                few_shot: FewShot::new(Vec::new(), None, &llm_cfg).await.unwrap(),
+                voting: VotingCfg::Majority,
+                prediction: PredictionCfg {
+                    strategy: PredictionStrategyCfg::SelfConsistency { samples, temperature: 0.8 },
+                    ..Default::default()
+                },
+                prompt: Prompt {
+                    system: None,
+                    prompt: String::from("Predict the relation."),
+                    format: settings::ResponseFormatCfg::KeyValue,
+                    arguments: ArgumentFieldsCfg::Raw,
+                    endpoint: settings::EndpointCfg::Generate,
+                },
+                tie_break_prompt: None,
+            }
+        }
+
+        fn argument(id: u32) -> models::Argument {
+            let info = models::SummarizedInfo { title: String::new(), summary: String::new(), quote: None, structure: None };
+
+            models::Argument::with_id(id, info, String::from("Taxes are too high."), None)
+        }
+
+        async fn predict(predictor: &Predictor) -> Result<llm::chat::Prediction, Error> {
+            predictor.predict_member_relation(&predictor.members[0], &predictor.prompt, &argument(1), &argument(2), &[], &[]).await
+        }
+
+        #[tokio::test]
+        async fn votes_over_successful_samples() {
+            let mut server = mockito::Server::new_async().await;
+            let failed = server.mock("POST", "/api/generate")
+                .with_status(400)
+                .with_body(r#"{"error": "bad request"}"#)
+                .expect(1)
+                .create_async()
+                .await;
+            let succeeded = server.mock("POST", "/api/generate")
+                .with_header("content-type", "application/json")
+                .with_body(serde_json::json!({
+                    "model": "test-model",
+                    "response": "Relation: Support",
+                    "done": true,
+                }).to_string())
+                .expect(2)
+                .create_async()
+                .await;
+
+            let prediction = predict(&predictor(server.url(), 3).await).await.unwrap();
+
+            failed.assert_async().await;
+            succeeded.assert_async().await;
+            assert_eq!(prediction.relation.relation_type, models::RelationType::Support);
+            assert_eq!(prediction.relation.confidence, 1.0);
+            assert_eq!(prediction.relation.votes.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn fails_when_every_sample_fails() {
+            let mut server = mockito::Server::new_async().await;
+            server.mock("POST", "/api/generate")
+                .with_status(400)
+                .with_body(r#"{"error": "bad request"}"#)
+                .expect(3)
+                .create_async()
+                .await;
+
+            assert!(predict(&predictor(server.url(), 3).await).await.is_err());
+        }
+    }
+
+    mod unstored_pairs {
+        use super::*;
+
+        #[test]
+        fn skips_pairs_stored_in_either_direction() {
+            let info = models::SummarizedInfo { title: String::new(), summary: String::new(), quote: None, structure: None };
+            let args: Vec<models::Argument> = (1..=3)
+                .map(|id| models::Argument::with_id(id, info.clone(), String::new(), None))
+                .collect();
+
+            let stored = models::Relation {
+                arg_a_id: 3,
+                arg_b_id: 1,
+                relation_type: models::RelationType::Support,
+                confidence: 1.0,
+                explanation: String::new(),
+                votes: Vec::new(),
+                contradicted: false,
+            };
+
+            assert_eq!(
+                unstored_pairs(vec![(0, 1), (0, 2), (1, 2)], &args, &[stored]),
+                vec![(0, 1), (1, 2)],
+            );
+        }
+    }
+
+    mod contradicts {
+        use super::*;
+
+        fn relation(relation_type: models::RelationType) -> models::Relation {
+            models::Relation {
+                arg_a_id: 1,
+                arg_b_id: 2,
+                relation_type,
+                confidence: 1.0,
+                explanation: String::new(),
+                votes: Vec::new(),
+                contradicted: false,
+            }
+        }
+
+        #[test]
+        fn detects_support_against_attack() {
+            assert!(contradicts(
+                &relation(models::RelationType::Support),
+                &relation(models::RelationType::Attack),
+            ));
+            assert!(contradicts(
+                &relation(models::RelationType::Attack),
+                &relation(models::RelationType::Support),
+            ));
+            assert!(contradicts(
+                &relation(models::RelationType::PartialSupport),
+                &relation(models::RelationType::Undercut),
+            ));
+        }
+
+        #[test]
+        fn accepts_consistent_relations() {
+            assert!(!contradicts(
+                &relation(models::RelationType::Support),
+                &relation(models::RelationType::Support),
+            ));
+            assert!(!contradicts(
+                &relation(models::RelationType::Attack),
+                &relation(models::RelationType::NotRelated),
+            ));
+        }
+    }
+
+    mod vote {
+        use super::*;
+
+        fn ballot(relation_type: models::RelationType, confidence: f32, weight: f32) -> Ballot {
+            Ballot {
+                relation: models::Relation {
+                    arg_a_id: 1,
+                    arg_b_id: 2,
+                    relation_type,
+                    confidence,
+                    explanation: String::new(),
+                    votes: Vec::new(),
+                    contradicted: false,
+                },
+                model: String::from("test-model"),
+                weight,
+            }
+        }
+
+        #[test]
+        fn majority_uses_agreement_as_confidence() {
+            let relation = vote(vec![
+                ballot(models::RelationType::NotRelated, 1.0, 1.0),
+                ballot(models::RelationType::Support, 1.0, 1.0),
+                ballot(models::RelationType::Support, 1.0, 1.0),
+                ballot(models::RelationType::Attack, 1.0, 1.0),
+            ], &VotingCfg::Majority).unwrap();
+
+            assert_eq!(relation.relation_type, models::RelationType::Support);
+            assert_eq!(relation.confidence, 0.5);
+            assert_eq!(relation.votes.len(), 4);
+            assert_eq!(relation.votes[0].model, "test-model");
+        }
+
+        #[test]
+        fn majority_breaks_ties_with_first_prediction() {
+            let relation = vote(vec![
+                ballot(models::RelationType::Attack, 1.0, 1.0),
+                ballot(models::RelationType::Support, 1.0, 1.0),
+            ], &VotingCfg::Majority).unwrap();
+
+            assert_eq!(relation.relation_type, models::RelationType::Attack);
+        }
+
+        #[test]
+        fn weighted_counts_weight_and_confidence() {
+            let relation = vote(vec![
+                ballot(models::RelationType::Attack, 1.0, 1.0),
+                ballot(models::RelationType::Attack, 0.5, 1.0),
+                ballot(models::RelationType::Support, 1.0, 3.0),
+            ], &VotingCfg::Weighted).unwrap();
+
+            assert_eq!(relation.relation_type, models::RelationType::Support);
+            assert_eq!(relation.confidence, 3.0 / 4.5);
+        }
+
+        #[test]
+        fn unanimous_discards_disagreements() {
+            assert!(vote(vec![
+                ballot(models::RelationType::Attack, 1.0, 1.0),
+                ballot(models::RelationType::Support, 1.0, 1.0),
+            ], &VotingCfg::Unanimous).is_none());
+
+            let relation = vote(vec![
+                ballot(models::RelationType::Attack, 1.0, 1.0),
+                ballot(models::RelationType::Attack, 1.0, 1.0),
+            ], &VotingCfg::Unanimous).unwrap();
+
+            assert_eq!(relation.confidence, 1.0);
+        }
+    }
 }
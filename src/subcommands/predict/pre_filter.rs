@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use crate::clients::llm::{self, ClientTrait};
+use crate::configuration::settings::PreFilterCfg;
+use crate::models;
+use crate::clients::llm::similarity::cosine_similarity;
+
+/// Select the pairs of arguments whose relations are worth predicting, as
+/// pairs of indices in `args` with the lowest index first.
+///
+/// Unless the pre-filter is disabled, the summary of every argument is
+/// embedded, with at most `max_requests` requests at once, and only the pairs
+/// of similar enough arguments are kept.
+pub async fn candidate_pairs(llm_client: &llm::Client, args: &[models::Argument], cfg: &PreFilterCfg, max_requests: usize) -> Result<Vec<(usize, usize)>, llm::Error> {
+    let total = args.len() * args.len().saturating_sub(1) / 2;
+
+    if let PreFilterCfg::None = cfg {
+        return Ok(all_pairs(args.len()))
+    }
+
+    let embeddings: Vec<Vec<f32>> = stream::iter(args.iter())
+        .map(|arg| llm_client.embed(&arg.summarized_info.summary))
+        .buffered(max_requests)
+        .try_collect()
+        .await?;
+
+    let pairs = select_pairs(&embeddings, cfg);
+
+    log::info!(
+        "pre-filter kept {} of {} pairs of arguments, {} pruned",
+        pairs.len(), total, total - pairs.len(),
+    );
+
+    Ok(pairs)
+}
+
+fn all_pairs(len: usize) -> Vec<(usize, usize)> {
+    (0..len)
+        .flat_map(|i| (i + 1..len).map(move |j| (i, j)))
+        .collect()
+}
+
+/// Select the pairs of similar embeddings with the given pre-filter.
+fn select_pairs(embeddings: &[Vec<f32>], cfg: &PreFilterCfg) -> Vec<(usize, usize)> {
+    match cfg {
+        PreFilterCfg::None => all_pairs(embeddings.len()),
+        PreFilterCfg::Threshold { similarity } => all_pairs(embeddings.len())
+            .into_iter()
+            .filter(|&(i, j)| cosine_similarity(&embeddings[i], &embeddings[j]) >= *similarity)
+            .collect(),
+        PreFilterCfg::TopK { k } => {
+            let mut pairs = BTreeSet::new();
+
+            for (i, embedding) in embeddings.iter().enumerate() {
+                let mut neighbours: Vec<(usize, f32)> = embeddings.iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(j, other)| (j, cosine_similarity(embedding, other)))
+                    .collect();
+
+                neighbours.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+                for &(j, _) in neighbours.iter().take(*k) {
+                    pairs.insert((i.min(j), i.max(j)));
+                }
+            }
+
+            pairs.into_iter().collect()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embeddings() -> Vec<Vec<f32>> {
+        vec![
+            vec![1.0, 0.0],
+            vec![0.9, 0.1],
+            vec![0.0, 1.0],
+            vec![-1.0, 0.0],
+        ]
+    }
+
+    mod candidate_pairs {
+        use super::*;
+        use crate::configuration::settings;
+
+        #[tokio::test]
+        async fn embeds_every_argument_with_the_given_client() {
+            let mut server = mockito::Server::new_async().await;
+            let mock = server.mock("POST", "/api/embed")
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"model": "test-model", "embeddings": [[1.0, 0.0]], "prompt_eval_count": 5}"#)
+                .expect(3)
+                .create_async()
+                .await;
+
+            let llm_client = llm::Client::new(&settings::LLMCfg::Ollama(settings::OllamaCfg {
+                uri: server.url(),
+                model: String::from("test-model"),
+                embedding_model: None,
+                structured_outputs: false,
+                options: settings::ModelOptions::default(),
+                summarize_options: settings::ModelOptions::default(),
+                predict_options: settings::ModelOptions::default(),
+                retry: settings::RetryCfg::default(),
+                context: settings::ContextCfg::default(),
+            }));
+
+            let info = models::SummarizedInfo { title: String::new(), summary: String::from("Taxes."), quote: None, structure: None };
+            let args: Vec<models::Argument> = (1..=3)
+                .map(|id| models::Argument::with_id(id, info.clone(), String::new(), None))
+                .collect();
+
+            let pairs = candidate_pairs(&llm_client, &args, &PreFilterCfg::Threshold { similarity: 0.5 }, 2)
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 2)]);
+            assert_eq!(llm_client.inference_stats().requests, 3);
+            assert_eq!(llm_client.inference_stats().prompt_tokens, 15);
+        }
+    }
+
+    mod select_pairs {
+        use super::*;
+
+        #[test]
+        fn keeps_every_pair_without_filter() {
+            assert_eq!(select_pairs(&embeddings(), &PreFilterCfg::None).len(), 6);
+        }
+
+        #[test]
+        fn keeps_pairs_above_threshold() {
+            let pairs = select_pairs(&embeddings(), &PreFilterCfg::Threshold { similarity: 0.5 });
+
+            assert_eq!(pairs, vec![(0, 1)]);
+        }
+
+        #[test]
+        fn keeps_nearest_neighbours() {
+            let pairs = select_pairs(&embeddings(), &PreFilterCfg::TopK { k: 1 });
+
+            assert_eq!(pairs, vec![(0, 1), (1, 2), (2, 3)]);
+        }
+    }
+}
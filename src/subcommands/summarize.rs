@@ -1,23 +1,17 @@
 use std::path::PathBuf;
+use futures::stream::{self, StreamExt};
 use tokio::fs;
-use serde::{Deserialize, Serialize};
 use serde_json;
 use thiserror::Error as ThisError;
 
 use crate::clients::{llm, repository, llm::ClientTrait, repository::RepositoryTrait};
-use crate::configuration::settings::{LLMCfg, RepositoryCfg, Prompt};
-use crate::models::Argument;
-
-/// Describe the content of the raw arguments passed through the input JSON
-/// file.
-#[derive(Deserialize, Serialize, Debug)]
-struct InputContentData {
-    /// The user-generated web content to extract arguments from as a raw
-    /// string.
-    content: String,
-}
+use crate::configuration::settings::{LLMCfg, RepositoryCfg, Prompt, ConcurrencyCfg, ExamplesCfg, MetricsCfg};
+use crate::models::{Argument, ExtractedPost, Post, SummarizedInfo};
+use crate::clients::llm::few_shot::{self, FewShot};
+use crate::subcommands::metrics;
 
 #[derive(Debug, ThisError)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("io error: {0}")]
     IOError(#[from] std::io::Error),
@@ -27,50 +21,171 @@ pub enum Error {
 
     #[error("repository error: {0}")]
     RepositoryError(#[from] repository::Error),
+
+    #[error("few-shot examples error: {0}")]
+    FewShotError(#[from] few_shot::Error),
 }
 
 pub struct SummarizeArgumentCfg {
     pub llm_cfg: LLMCfg,
     pub repo_cfg: RepositoryCfg,
     pub prompt: Prompt,
+    pub reduce_prompt: Option<Prompt>,
+    pub examples: Option<ExamplesCfg>,
+    pub concurrency: ConcurrencyCfg,
+    pub metrics: MetricsCfg,
     pub file_path: PathBuf,
 }
 
 pub async fn summarize_arguments(cfg: SummarizeArgumentCfg) -> Result<(), Error> {
-    let content_inputs: Vec<InputContentData> = {
+    let posts: Vec<Post> = {
         let data = fs::read_to_string(cfg.file_path).await?;
 
         serde_json::from_str(&data)?
     };
 
-    let llm_client = llm::Client::new(&cfg.llm_cfg);
+    let examples = few_shot::load_examples(cfg.examples.as_ref()).await?;
+
+    let few_shot = &FewShot::new(examples.summarize, cfg.examples.as_ref(), &cfg.llm_cfg).await?;
+    let llm_client = &llm::Client::new(&cfg.llm_cfg);
+    let prompt = &cfg.prompt;
+    let reduce_prompt = cfg.reduce_prompt.as_ref();
+    let repairs = &llm::RepairCounter::default();
     let mut repo_client = repository::Repository::new(&cfg.repo_cfg)
         .await?;
 
-    // I've decided to send to send each elements as a separate requests to
-    // simplify the development, and the design of the request handling.
-    for input in content_inputs {
-        match llm_client.summarize(&cfg.prompt, input.content.clone()).await {
-            Ok(info) => {
-                log::info!("sucessfully summarized argument");
+    // Each element is sent as a separate request, up to `max_requests` of them
+    // running at once, and their results are collected in input order so that
+    // the posts are stored in the order of the source file.
+    let mut summaries = stream::iter(posts)
+        .map(|post| async move {
+            let summary = repairs.track(async {
+                match few_shot.select(&post.content).await {
+                    Ok(examples) => llm_client.summarize(prompt, reduce_prompt, &post, &examples).await,
+                    Err(e) => Err(e),
+                }
+            }).await;
 
-                let argument = Argument::new(info, input.content);
+            (summary, post)
+        })
+        .buffered(cfg.concurrency.max_requests);
 
-                if let Err(ref e) = repo_client.add_argument(argument).await {
-                    log::error!("failed to create argument in Neo4j database: {}", e);
-                };
+    let mut batch = Vec::with_capacity(cfg.concurrency.batch_size);
+
+    while let Some((result, post)) = summaries.next().await {
+        match result {
+            Ok(infos) if infos.is_empty() => {
+                log::warn!("no argument found in post, skipping it");
+            },
+            Ok(infos) => {
+                log::info!("sucessfully summarized {} arguments", infos.len());
+
+                let single = infos.len() == 1;
+                let arguments = infos.into_iter()
+                    .map(|info| argument(&post, info, single))
+                    .collect();
+
+                batch.push(ExtractedPost { post, arguments });
             },
             Err(e) => {
                 log::error!("{}", e);
             },
         };
+
+        if batch.len() >= cfg.concurrency.batch_size {
+            add_posts(&mut repo_client, std::mem::take(&mut batch)).await;
+        }
     }
 
+    add_posts(&mut repo_client, batch).await;
+
+    let repairs = repairs.stats();
+
+    log::info!("{} posts needed repair, {} of them were repaired", repairs.needed, repairs.repaired);
+
+    metrics::report(llm_client.inference_stats(), &cfg.metrics).await;
+
     Ok(())
 }
 
+/// Build an argument of a post, its raw content being the passage quoted by
+/// the model if it appears verbatim in the post. The only argument of a post
+/// falls back to the whole post, as do the others with a warning.
+fn argument(post: &Post, info: SummarizedInfo, single: bool) -> Argument {
+    let located = info.quote.as_deref()
+        .map(str::trim)
+        .filter(|quote| !quote.is_empty())
+        .and_then(|quote| post.offset_of(quote).map(|offset| (String::from(quote), offset)));
+
+    match located {
+        Some((quote, offset)) => Argument::new(info, quote, Some(offset)),
+        None if single => Argument::new(info, post.content.clone(), Some(0)),
+        None => {
+            log::warn!("passage of argument \"{}\" not found in its post, keeping the whole post", info.title);
+
+            Argument::new(info, post.content.clone(), None)
+        },
+    }
+}
+
+/// Store a batch of summarized posts, logging the failure of the batch
+/// rather than aborting the remaining summaries.
+async fn add_posts(repo_client: &mut repository::Repository, posts: Vec<ExtractedPost>) {
+    if posts.is_empty() {
+        return
+    }
+
+    let count = posts.len();
+
+    if let Err(ref e) = repo_client.add_posts(posts).await {
+        log::error!("failed to create {} posts in Neo4j database: {}", count, e);
+    };
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     mod retrieve_arguments {
     }
+
+    mod argument {
+        use super::*;
+
+        fn post(content: &str) -> Post {
+            Post {
+                content: String::from(content),
+                author: None,
+                thread_context: None,
+            }
+        }
+
+        fn info(quote: Option<&str>) -> SummarizedInfo {
+            SummarizedInfo {
+                title: String::from("Taxes"),
+                summary: String::from("Taxes should be lower."),
+                quote: quote.map(String::from),
+                structure: None,
+            }
+        }
+
+        #[test]
+        fn keeps_located_quote() {
+            let argument = argument(&post("Hélas, taxes are too high. Cut spending."), info(Some(" taxes are too high. ")), false);
+
+            assert_eq!(argument.raw, "taxes are too high.");
+            assert_eq!(argument.offset, Some(7));
+        }
+
+        #[test]
+        fn falls_back_to_whole_post() {
+            let post = post("Taxes are too high. Cut spending.");
+
+            let single = argument(&post, info(None), true);
+            let several = argument(&post, info(Some("taxes are way too high")), false);
+
+            assert_eq!((single.raw.as_str(), single.offset), (post.content.as_str(), Some(0)));
+            assert_eq!((several.raw.as_str(), several.offset), (post.content.as_str(), None));
+        }
+    }
 }